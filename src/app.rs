@@ -1,10 +1,11 @@
-use std::{net::SocketAddr, sync::{Arc, Mutex}};
+use std::{path::Path, sync::{Arc, Mutex}};
 
-use egui::{Layout, RichText, Color32};
+use egui::{RichText, Color32};
 use egui_miniquad as emq;
 use miniquad as mq;
 
-use crate::{multiplayer::{client::{Client, start_client}, server::{Server, start_server}}, game::Game};
+use crate::{util::config::GameConfig, multiplayer::{client::{Client, start_client}, server::{Server, start_server}, ClientMsg}, game::{Game, GameOutcome, save::AUTOSAVE_FILE, replay::{Replay, ReplayStates, REPLAY_FILE}, notation::check_player_name, disease::DiseaseKind, cards::{Action, PlayCard, Event, CardIdx}, turn::Turn, player::{Profession, PlayerId}, city::CityIdx, world::MAX_RESEARCH_STATIONS}};
+use strum::IntoEnumIterator;
 
 #[derive(PartialEq)]
 enum PlayerType {
@@ -16,18 +17,44 @@ enum PlayerType {
 enum Stage {
     MainScreen,
     Lobby,
-    Game(PlayerType),
+    Game,
+    GameOver(GameOutcome),
+    Replay,
 }
 
 struct AppUi {
     stage: Stage,
     name: String,
+    name_error: Option<String>,
     host: String,
     port: String,
+    // Empty for a random seed
+    seed: String,
+    // Save file the host resumes instead of starting a new game
+    save_path: String,
+    load_game: bool,
+
+    replay_path: String,
+    replay_error: Option<String>,
+    replay: Option<ReplayViewer>,
     kind: PlayerType,
     connected: bool,
 
     connect_fail: bool,
+
+    // Cards picked in the discard phase
+    selected_cards: Vec<PlayCard>,
+    event_params: EventParams,
+}
+
+// Targets picked for the event cards before playing them
+#[derive(Default)]
+struct EventParams {
+    city: CityIdx,
+    relocate: Option<CityIdx>,
+    pawn: PlayerId,
+    infection_card: CardIdx,
+    forecast: Vec<CardIdx>,
 }
 
 pub struct App {
@@ -46,16 +73,399 @@ impl App {
             ui: AppUi {
                 stage: Stage::MainScreen,
                 name: String::default(),
+                name_error: None,
                 host: "127.0.0.1".to_string(),
                 port: "6969".to_string(),
+                seed: String::default(),
+                save_path: AUTOSAVE_FILE.to_string(),
+                load_game: false,
+                replay_path: REPLAY_FILE.to_string(),
+                replay_error: None,
+                replay: None,
                 kind: PlayerType::None,
                 connected: false,
                 connect_fail: false,
+                selected_cards: Vec::new(),
+                event_params: Default::default(),
             },
         }
     }
 }
 
+fn role_ui(ui: &mut egui::Ui, client: &Arc<Mutex<Client>>) {
+    let mut client = client.lock().unwrap();
+    let me = client.player.id;
+
+    let current = match client.game.players.get(me) {
+        Some(player) => player.profession,
+        None => return,
+    };
+
+    let mut chosen = current;
+    egui::ComboBox::from_label("Role")
+        .selected_text(format!("{:?}", current))
+        .show_ui(ui, |ui| {
+            for role in Profession::iter() {
+                let taken = role != Profession::None && client.game.players.iter().any(|p| p.profession == role && p.id != me);
+                ui.add_enabled_ui(!taken, |ui| {
+                    ui.selectable_value(&mut chosen, role, format!("{:?}", role));
+                });
+            }
+        });
+    ui.label(chosen.description());
+
+    if chosen != current {
+        client.send(ClientMsg::ChooseRole(chosen));
+    }
+}
+
+fn actions_ui(ui: &mut egui::Ui, client: &Arc<Mutex<Client>>, selected_cards: &mut Vec<PlayCard>) {
+    let mut client = client.lock().unwrap();
+    let me = client.player.id;
+
+    if let Some(player) = client.game.players.get(me) {
+        ui.label(format!("Role: {:?} - {}", player.profession, player.profession.description()));
+        if let Some(event) = &player.stored_event {
+            ui.label(format!("Stored event: {}", event.card().title));
+        }
+        ui.label("Hand:");
+        for card in player.cards.iter() {
+            ui.label(client.game.describe_card(card));
+        }
+        ui.separator();
+    }
+
+    if let Some(e) = &client.last_error {
+        ui.label(RichText::new(format!("Invalid action: {e}")).color(Color32::RED));
+    }
+
+    if let Some(pending) = client.consent_prompt.clone() {
+        ui.label(format!("{} wants to: {}",
+            client.game.players[pending.player].name,
+            client.game.describe_request(&pending.request)));
+
+        ui.horizontal(|ui| {
+            if ui.button("Accept").clicked() {
+                client.send(ClientMsg::Consent(true));
+                client.consent_prompt = None;
+            }
+            if ui.button("Decline").clicked() {
+                client.send(ClientMsg::Consent(false));
+                client.consent_prompt = None;
+            }
+        });
+        return;
+    }
+
+    if let Turn::Discard(player, _) = &client.game.turn {
+        if *player != me {
+            ui.label(format!("Waiting for {} to discard...", client.game.players[*player].name));
+            return;
+        }
+
+        let over_limit = client.game.players[me].cards_over_limit();
+        ui.label(format!("Over the hand limit, pick {over_limit} cards to discard:"));
+        for card in client.game.players[me].cards.clone() {
+            let mut selected = selected_cards.contains(&card);
+            if ui.checkbox(&mut selected, client.game.describe_card(&card)).changed() {
+                if selected {
+                    selected_cards.push(card);
+                } else {
+                    selected_cards.retain(|c| *c != card);
+                }
+            }
+        }
+
+        if ui.add_enabled(selected_cards.len() == over_limit, egui::Button::new("Discard")).clicked() {
+            client.send(ClientMsg::CardDiscarded(std::mem::take(selected_cards)));
+        }
+        return;
+    }
+
+    if let Turn::EventWindow(_, waiting, _) = &client.game.turn {
+        match client.event_prompt {
+        Some(hash) if waiting.contains(&me) => {
+            ui.label("You may play an event card before the game goes on.");
+            if ui.button("Pass").clicked() {
+                client.send(ClientMsg::RejectEvent(hash));
+                client.event_prompt = None;
+            }
+        },
+        _ => { ui.label("Waiting for other players to play events..."); },
+        }
+        return;
+    }
+
+    let actions = client.game.legal_actions(me);
+    if actions.is_empty() {
+        ui.label("Waiting for other players...");
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(client.game.can_undo(), egui::Button::new("Undo")).clicked() {
+            client.send(ClientMsg::Undo);
+        }
+        if ui.add_enabled(client.game.can_redo(), egui::Button::new("Redo")).clicked() {
+            client.send(ClientMsg::Redo);
+        }
+    });
+
+    // Cures are listed once per disease, with the cards to discard picked below
+    let mut cures: Vec<DiseaseKind> = Vec::new();
+    for action in actions.iter() {
+        match action {
+        Action::DiscoverCure(kind, _) => if !cures.contains(kind) {
+            cures.push(*kind);
+        },
+        _ => if ui.button(client.game.describe_action(action)).clicked() {
+            client.send(ClientMsg::Action(action.clone()));
+        },
+        }
+    }
+
+    for kind in cures {
+        ui.separator();
+        ui.label(format!("Pick the cards to discard for the {kind:?} cure:"));
+        let hand = client.game.players[me].city_cards(&client.game.world, kind);
+        for city in hand.iter() {
+            let card = PlayCard::City(*city);
+            let mut selected = selected_cards.contains(&card);
+            if ui.checkbox(&mut selected, client.game.describe_card(&card)).changed() {
+                if selected {
+                    selected_cards.push(card);
+                } else {
+                    selected_cards.retain(|c| *c != card);
+                }
+            }
+        }
+
+        let chosen = hand.into_iter().filter(|city| selected_cards.contains(&PlayCard::City(*city))).collect();
+        let cure = Action::DiscoverCure(kind, chosen);
+        if ui.add_enabled(actions.contains(&cure), egui::Button::new(format!("Discover cure for {kind:?}"))).clicked() {
+            selected_cards.clear();
+            client.send(ClientMsg::Action(cure));
+        }
+    }
+}
+
+// Number of game events shown in the log panel
+const LOG_LINES: usize = 15;
+
+fn log_ui(ui: &mut egui::Ui, game: &Game) {
+    for event in game.log.iter().rev().take(LOG_LINES) {
+        ui.label(game.describe_event(event));
+    }
+}
+
+// State of the board, for watching replays
+fn board_ui(ui: &mut egui::Ui, game: &Game) {
+    ui.label(format!("Outbreaks: {}, infection rate: {}", game.world.outbreaks, game.infection_rate()));
+    if let Some(strain) = &game.virulent {
+        let effects: Vec<&str> = strain.effects.iter().map(|effect| effect.title()).collect();
+        ui.label(format!("Virulent strain: {:?} ({})", strain.kind, effects.join(", ")));
+    }
+    for kind in game.world.diseases.iter().copied() {
+        let status = format!("{:?}: {:?}, {} cubes left", kind, game.world.cure_status(kind), game.world.cubes_left(kind));
+        ui.label(RichText::new(status).color(disease_color(kind)));
+    }
+    ui.separator();
+
+    for player in game.players.iter() {
+        let cards: Vec<String> = player.cards.iter().map(|card| game.describe_card(card)).collect();
+        ui.label(format!("{} ({:?}) in {}: {}",
+            player.name,
+            player.profession,
+            game.world.cities[player.current_city].name,
+            cards.join(", ")));
+    }
+    ui.separator();
+
+    for city in game.world.cities.iter() {
+        let station = if game.world.has_research_station(city.index) { " (Research station)" } else { "" };
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("{}{}", city.name, station)).color(disease_color(city.kind)));
+            for kind in game.world.diseases.iter().copied().filter(|kind| city.cubes(*kind) > 0) {
+                ui.label(RichText::new(format!("{:?}: {}", kind, city.cubes(kind))).color(disease_color(kind)));
+            }
+        });
+    }
+}
+
+// Colour of the cubes of each disease, lightened where the dark theme needs it
+fn disease_color(kind: DiseaseKind) -> Color32 {
+    match kind {
+    DiseaseKind::Red => Color32::RED,
+    DiseaseKind::Blue => Color32::LIGHT_BLUE,
+    DiseaseKind::Yellow => Color32::YELLOW,
+    DiseaseKind::Black => Color32::GRAY,
+    DiseaseKind::Purple => Color32::from_rgb(170, 90, 230),
+    }
+}
+
+// Every state of a replayed game, one per input
+struct ReplayViewer {
+    states: ReplayStates,
+    current: usize,
+}
+
+impl ReplayViewer {
+    fn turn(&self, state: usize) -> (usize, PlayerId) {
+        self.states.turn(state)
+    }
+
+    // First state of the turn `state` is in, or of the turn before when
+    // `state` already starts one
+    fn previous_turn(&self) -> usize {
+        let mut state = self.current.saturating_sub(1);
+        let turn = self.turn(state);
+        while state > 0 && self.turn(state - 1) == turn {
+            state -= 1;
+        }
+        state
+    }
+
+    fn next_turn(&self) -> usize {
+        let turn = self.turn(self.current);
+        (self.current..=self.states.last())
+            .find(|state| self.turn(*state) != turn)
+            .unwrap_or(self.states.last())
+    }
+}
+
+fn replay_ui(ui: &mut egui::Ui, viewer: &mut ReplayViewer) {
+    let last = viewer.states.last();
+    let (turn, player) = viewer.turn(viewer.current);
+    let name = viewer.states.state(viewer.current).players.get(player).map(|p| p.name.clone()).unwrap_or_default();
+
+    if let Some(e) = &viewer.states.diverged {
+        ui.label(RichText::new(e.to_string()).color(Color32::RED));
+    }
+
+    ui.label(format!("Turn {} - {}", turn, name));
+    ui.add(egui::Slider::new(&mut viewer.current, 0..=last).text("Step"));
+    ui.horizontal(|ui| {
+        if ui.button("<< Turn").clicked() {
+            viewer.current = viewer.previous_turn();
+        }
+        if ui.button("< Step").clicked() {
+            viewer.current = viewer.current.saturating_sub(1);
+        }
+        if ui.button("Step >").clicked() {
+            viewer.current = (viewer.current + 1).min(last);
+        }
+        if ui.button("Turn >>").clicked() {
+            viewer.current = viewer.next_turn();
+        }
+    });
+    ui.separator();
+
+    board_ui(ui, viewer.states.state(viewer.current));
+}
+
+fn pick_ui(ui: &mut egui::Ui, id: &str, selected: &mut usize, options: Vec<(usize, String)>) {
+    let text = options.iter().find(|(value, _)| value == selected).map(|(_, name)| name.clone()).unwrap_or_default();
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text)
+        .show_ui(ui, |ui| {
+            for (value, name) in options {
+                ui.selectable_value(selected, value, name);
+            }
+        });
+}
+
+fn events_ui(ui: &mut egui::Ui, client: &Arc<Mutex<Client>>, params: &mut EventParams) {
+    let mut client = client.lock().unwrap();
+    let me = client.player.id;
+
+    let player = match client.game.players.get(me) {
+        Some(player) => player,
+        None => return,
+    };
+
+    let mut events: Vec<Event> = player.cards.iter().filter_map(|card| match card {
+        PlayCard::Event(event) => Some(event.clone()),
+        _ => None,
+    }).collect();
+    events.extend(player.stored_event.clone());
+    if events.is_empty() {
+        return;
+    }
+
+    let game = &client.game;
+    let cities: Vec<(usize, String)> = game.world.cities.iter().map(|c| (c.index, c.name.clone())).collect();
+    let infection_name = |card: CardIdx| game.describe_infection_card(card);
+
+    let mut played = None;
+    ui.label("Events:");
+    for event in events {
+        let card = event.card();
+        ui.collapsing(card.title, |ui| {
+            ui.label(card.effect);
+
+            let event = match event {
+            Event::GovermentGrant(..) => {
+                pick_ui(ui, "grant city", &mut params.city, cities.clone());
+                if game.world.research_stations.len() >= MAX_RESEARCH_STATIONS {
+                    let stations = game.world.research_stations.iter().map(|c| (*c, cities[*c].1.clone())).collect();
+                    let mut from = params.relocate.unwrap_or(game.world.research_stations[0]);
+                    ui.label("Move the research station from:");
+                    pick_ui(ui, "grant relocate", &mut from, stations);
+                    params.relocate = Some(from);
+                } else {
+                    params.relocate = None;
+                }
+                Event::GovermentGrant(params.city, params.relocate)
+            },
+            Event::Forecast(_) => {
+                let mut top = game.forecast_cards();
+                let mut order = params.forecast.clone();
+                top.sort_unstable();
+                order.sort_unstable();
+                if top != order {
+                    params.forecast = game.forecast_cards();
+                }
+
+                ui.label("Top of the infection deck first:");
+                let count = params.forecast.len();
+                for i in 0..count {
+                    ui.horizontal(|ui| {
+                        ui.label(infection_name(params.forecast[i]));
+                        if i > 0 && ui.small_button("Up").clicked() {
+                            params.forecast.swap(i, i - 1);
+                        }
+                        if i + 1 < count && ui.small_button("Down").clicked() {
+                            params.forecast.swap(i, i + 1);
+                        }
+                    });
+                }
+                Event::Forecast(params.forecast.clone())
+            },
+            Event::Airlift(..) => {
+                let pawns = game.players.iter().map(|p| (p.id, p.name.clone())).collect();
+                pick_ui(ui, "airlift pawn", &mut params.pawn, pawns);
+                pick_ui(ui, "airlift city", &mut params.city, cities.clone());
+                Event::Airlift(params.pawn, params.city)
+            },
+            Event::OneQuietNight => Event::OneQuietNight,
+            Event::ResilientPopulation(_) => {
+                let discard = game.world.disease_deck.cards_discard.iter().map(|c| (*c, infection_name(*c))).collect();
+                pick_ui(ui, "resilient card", &mut params.infection_card, discard);
+                Event::ResilientPopulation(params.infection_card)
+            },
+            };
+
+            if ui.button("Play").clicked() {
+                played = Some(event);
+            }
+        });
+    }
+
+    if let Some(event) = played {
+        client.send(ClientMsg::Event(event));
+    }
+}
+
 impl mq::EventHandler for App {
     fn update(&mut self, _: &mut mq::Context) { }
 
@@ -79,8 +489,56 @@ impl mq::EventHandler for App {
                         });
         
                         if ui.button("Enter").clicked() {
+                            match check_player_name(&self.ui.name) {
+                            Ok(()) => {
+                                self.ui.name_error = None;
+                                self.ui.load_game = false;
+                                self.ui.stage = Stage::Lobby;
+                            },
+                            Err(e) => self.ui.name_error = Some(e),
+                            }
+                        }
+                        if let Some(e) = &self.ui.name_error {
+                            ui.label(RichText::new(e).color(Color32::RED));
+                        }
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Save file:");
+                            ui.text_edit_singleline(&mut self.ui.save_path);
+                        });
+                        if ui.button("Load game").clicked() {
+                            self.ui.load_game = true;
+                            self.ui.kind = PlayerType::Host;
                             self.ui.stage = Stage::Lobby;
                         }
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Replay file:");
+                            ui.text_edit_singleline(&mut self.ui.replay_path);
+                        });
+                        if ui.button("Watch replay").clicked() {
+                            let path = Path::new(&self.ui.replay_path);
+                            // Game notation is text, naming the cities of the default config
+                            let replay = if path.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+                                GameConfig::from_file(Path::new("assets/config.yaml"))
+                                    .and_then(|config| Replay::load_notation(path, config))
+                            } else {
+                                Replay::load(path)
+                            };
+                            match replay {
+                            Ok(replay) => {
+                                self.ui.replay = Some(ReplayViewer { states: ReplayStates::new(replay), current: 0 });
+                                self.ui.replay_error = None;
+                                self.ui.stage = Stage::Replay;
+                            },
+                            Err(e) => self.ui.replay_error = Some(e.to_string()),
+                            }
+                        }
+                        if let Some(e) = &self.ui.replay_error {
+                            ui.label(RichText::new(format!("Failed to load the replay: {e}")).color(Color32::RED));
+                        }
                     }); // area
                 }); // panel
             }
@@ -96,11 +554,18 @@ impl mq::EventHandler for App {
                             }
 
                             if self.ui.connected {
+                                if self.client.lock().unwrap().started {
+                                    self.ui.stage = Stage::Game;
+                                    return;
+                                }
+
                                 ui.label(RichText::new("Waiting for host to start the game...").color(Color32::RED));
 
+                                role_ui(ui, &self.client);
+
                                 ui.label("Connected players:");
                                 for p in self.client.lock().unwrap().game.players.iter() {
-                                    ui.label(format!("{}", p.name));
+                                    ui.label(&p.name);
                                 }
                                 return;
                             }
@@ -117,7 +582,7 @@ impl mq::EventHandler for App {
                             if ui.button("Connect").clicked() {
                                 let addr = format!("{}:{}", self.ui.host, self.ui.port);
                                 println!("Client addr: {addr}");
-                                if let Some(client) = Client::new(&self.ui.name, &addr, false) {
+                                if let Some(client) = Client::new(&self.ui.name, &addr) {
                                     self.client = Arc::new(Mutex::new(client));
                                     start_client(self.client.clone());
                                     self.ui.connected = true;
@@ -133,13 +598,17 @@ impl mq::EventHandler for App {
 
                             if self.ui.connected {
                                 if ui.button("Start Game").clicked() {
-
-                                    self.ui.stage = Stage::Game(PlayerType::Host);
+                                    self.server.lock().unwrap().start_game();
+                                    self.ui.stage = Stage::Game;
                                 }
+
+                                ui.label(format!("Seed: {}", self.server.lock().unwrap().game.rng.seed()));
+
+                                role_ui(ui, &self.client);
         
                                 ui.label("Connected players:");
                                 for p in self.server.lock().unwrap().game.players.iter() {
-                                    ui.label(format!("{}", p.name));
+                                    ui.label(&p.name);
                                 }
                                 return;
                             }
@@ -148,8 +617,22 @@ impl mq::EventHandler for App {
                                 ui.label("Port:");
                                 ui.text_edit_singleline(&mut self.ui.port);
                             });
+                            if self.ui.load_game {
+                                ui.label(format!("Resuming {}, players join with their saved names", self.ui.save_path));
+                            } else {
+                                ui.horizontal(|ui| {
+                                    ui.label("Seed:");
+                                    ui.text_edit_singleline(&mut self.ui.seed);
+                                });
+                            }
                             if ui.button("Start Server").clicked() {
-                                if let Some(server) = Server::new(&self.ui.port) {
+                                let server = if self.ui.load_game {
+                                    Server::load(&self.ui.port, Path::new(&self.ui.save_path))
+                                } else {
+                                    Server::new(&self.ui.port, self.ui.seed.trim().parse().ok())
+                                };
+
+                                if let Some(server) = server {
                                     self.server = Arc::new(Mutex::new(server));
                                     start_server(self.server.clone());
 
@@ -159,7 +642,7 @@ impl mq::EventHandler for App {
 
                                     let mut client_addr = "127.0.0.1:".to_string();
                                     client_addr.push_str(&self.ui.port);
-                                    if let Some(client) = Client::new(&self.ui.name, &client_addr, false) {
+                                    if let Some(client) = Client::new(&self.ui.name, &client_addr) {
                                         println!("Starting host-client...");
                                         self.client = Arc::new(Mutex::new(client));
                                         start_client(self.client.clone());
@@ -186,24 +669,80 @@ impl mq::EventHandler for App {
                     }); // area
                 });
             }
-            Stage::Game(kind) => {
+            Stage::Game => {
+                if let Some(outcome) = self.client.lock().unwrap().game.outcome {
+                    self.ui.stage = Stage::GameOver(outcome);
+                    return;
+                }
+
                 match self.ui.kind {
                     PlayerType::Guest => {
                         egui::Window::new("Game Guest").show(egui_ctx, |ui| {
-                            if ui.button("play").clicked() {
-                                println!("Playing...");
-                            }
+                            actions_ui(ui, &self.client, &mut self.ui.selected_cards);
+                            events_ui(ui, &self.client, &mut self.ui.event_params);
                         });
                     },
                     PlayerType::Host => {
                         egui::Window::new("Game Host").show(egui_ctx, |ui| {
-                            if ui.button("play").clicked() {
-                                println!("Playing...");
-                            }
+                            actions_ui(ui, &self.client, &mut self.ui.selected_cards);
+                            events_ui(ui, &self.client, &mut self.ui.event_params);
                         });
                     },
                     _ => { panic!("Something's not right!"); },
                 };
+
+                egui::Window::new("Log").show(egui_ctx, |ui| {
+                    log_ui(ui, &self.client.lock().unwrap().game);
+                });
+            },
+            Stage::Replay => {
+                egui::Window::new("Replay").show(egui_ctx, |ui| {
+                    if ui.button("Main menu").clicked() {
+                        self.ui.replay = None;
+                        self.ui.stage = Stage::MainScreen;
+                        return;
+                    }
+
+                    if let Some(viewer) = &mut self.ui.replay {
+                        replay_ui(ui, viewer);
+                    }
+                });
+
+                if let Some(viewer) = &mut self.ui.replay {
+                    egui::Window::new("Log").show(egui_ctx, |ui| {
+                        log_ui(ui, viewer.states.state(viewer.current));
+                    });
+                }
+            },
+            Stage::GameOver(outcome) => {
+                let outcome = *outcome;
+                egui::CentralPanel::default().show(egui_ctx, |_| { // panel
+                    egui::Area::new("game over")
+                        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                        .show(egui_ctx, |ui| { // area
+                        let color = if outcome == GameOutcome::Victory { Color32::GREEN } else { Color32::RED };
+                        ui.label(RichText::new(outcome.to_string()).color(color).heading());
+
+                        if ui.button("Copy game notation").clicked() {
+                            let notation = self.client.lock().unwrap().game.to_notation();
+                            ui.output_mut(|o| o.copied_text = notation);
+                        }
+
+                        if ui.button("Main menu").clicked() {
+                            self.client.lock().unwrap().stop();
+                            if self.ui.kind == PlayerType::Host {
+                                self.server.lock().unwrap().stop();
+                            }
+
+                            self.client = Default::default();
+                            self.server = Default::default();
+                            self.ui.kind = PlayerType::None;
+                            self.ui.connected = false;
+                            self.ui.connect_fail = false;
+                            self.ui.stage = Stage::MainScreen;
+                        }
+                    }); // area
+                }); // panel
             },
             }
         });
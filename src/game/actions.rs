@@ -0,0 +1,952 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Game, GameOutcome, log::GameEvent, cards::{Action, PlayCard, DiseaseCard, ShareDirection, Event, CardIdx}, city::CityIdx, player::{PlayerId, Profession}, turn::Turn, disease::{DiseaseKind, CureStatus}, world::MAX_RESEARCH_STATIONS};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ActionOutcome {
+    Moved { pawn: PlayerId, from: CityIdx, to: CityIdx },
+    Treated { city: CityIdx, kind: DiseaseKind, removed: u8 },
+    Built { city: CityIdx, relocated_from: Option<CityIdx> },
+    Cured(DiseaseKind),
+    Shared { from: PlayerId, to: PlayerId, card: CityIdx },
+    EventStored(Event),
+    EventPlayed(Event),
+    // The action waits for the consent of another player
+    AwaitingConsent(PlayerId),
+    Declined(PlayerId),
+}
+
+// What a player asks another one to agree to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    Action(Action),
+    // Airlift of the other player's pawn
+    Event(Event),
+}
+
+// An action which needs another player to agree before it happens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingAction {
+    pub player: PlayerId,
+    pub target: PlayerId,
+    pub request: Request,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ActionError {
+    GameOver,
+    InvalidPlayer(PlayerId),
+    NotYourTurn(PlayerId),
+    NoActionsLeft,
+    InvalidCity(CityIdx),
+    AlreadyThere(CityIdx),
+    NotConnected(CityIdx, CityIdx),
+    MissingCard(CityIdx),
+    NotInCity(CityIdx),
+    NoCubes(CityIdx, DiseaseKind),
+    AlreadyCured(DiseaseKind),
+    NotInPlay(DiseaseKind),
+    NotEnoughCards(DiseaseKind, usize),
+    NotCuredBy(CityIdx, DiseaseKind),
+    NotInSameCity(PlayerId),
+    NoResearchStation(CityIdx),
+    ResearchStationExists(CityIdx),
+    NoResearchStationsLeft,
+    InvalidRelocation(CityIdx),
+    CannotShare(CityIdx),
+    AwaitingConsent(PlayerId),
+    NothingToAnswer(PlayerId),
+    Declined(PlayerId),
+    NotInHand(PlayCard),
+    NotAllowed(Profession),
+    EventAlreadyStored,
+    NotInDiscard(Event),
+    NotDiscarding(PlayerId),
+    MustDiscard(usize),
+    InvalidForecast,
+    NotInInfectionDiscard(CardIdx),
+    NoEventWindow(PlayerId),
+    NothingToUndo,
+    NothingToRedo,
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+        ActionError::GameOver => write!(f, "the game is over"),
+        ActionError::InvalidPlayer(p) => write!(f, "there is no player {p}"),
+        ActionError::NotYourTurn(p) => write!(f, "it is not player {p}'s turn"),
+        ActionError::NoActionsLeft => write!(f, "no actions left this turn"),
+        ActionError::InvalidCity(c) => write!(f, "there is no city {c}"),
+        ActionError::AlreadyThere(c) => write!(f, "already in city {c}"),
+        ActionError::NotConnected(from, to) => write!(f, "city {from} is not connected to city {to}"),
+        ActionError::MissingCard(c) => write!(f, "the card of city {c} is not in hand"),
+        ActionError::NotInCity(c) => write!(f, "the player is not in city {c}"),
+        ActionError::NoCubes(c, kind) => write!(f, "city {c} has no {kind:?} cubes"),
+        ActionError::AlreadyCured(kind) => write!(f, "{kind:?} is already cured"),
+        ActionError::NotInPlay(kind) => write!(f, "{kind:?} is not in this game"),
+        ActionError::NotEnoughCards(kind, n) => write!(f, "the cure for {kind:?} takes {n} cards"),
+        ActionError::NotCuredBy(c, kind) => write!(f, "the card of city {c} does not count towards the cure for {kind:?}"),
+        ActionError::NotInSameCity(p) => write!(f, "player {p} is in another city"),
+        ActionError::NoResearchStation(c) => write!(f, "city {c} has no research station"),
+        ActionError::ResearchStationExists(c) => write!(f, "city {c} already has a research station"),
+        ActionError::NoResearchStationsLeft => write!(f, "all {MAX_RESEARCH_STATIONS} research stations are built, one has to be moved"),
+        ActionError::InvalidRelocation(c) => write!(f, "cannot move the research station from city {c}"),
+        ActionError::CannotShare(c) => write!(f, "only the card of the current city can be shared, not city {c}"),
+        ActionError::AwaitingConsent(p) => write!(f, "waiting for player {p} to agree"),
+        ActionError::NothingToAnswer(p) => write!(f, "player {p} was not asked anything"),
+        ActionError::Declined(p) => write!(f, "player {p} declined"),
+        ActionError::NotInHand(card) => write!(f, "{card:?} is not in hand"),
+        ActionError::NotAllowed(role) => write!(f, "{role:?} cannot do that"),
+        ActionError::EventAlreadyStored => write!(f, "an event card is already stored"),
+        ActionError::NotInDiscard(event) => write!(f, "{event:?} is not in the discard pile"),
+        ActionError::NotDiscarding(p) => write!(f, "player {p} does not have to discard"),
+        ActionError::MustDiscard(n) => write!(f, "exactly {n} cards have to be discarded"),
+        ActionError::InvalidForecast => write!(f, "the forecast has to reorder the top infection cards"),
+        ActionError::NotInInfectionDiscard(c) => write!(f, "infection card {c} is not in the discard pile"),
+        ActionError::NoEventWindow(p) => write!(f, "player {p} is not asked to play an event"),
+        ActionError::NothingToUndo => write!(f, "there is no action to undo"),
+        ActionError::NothingToRedo => write!(f, "there is no action to redo"),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+// Every way to pick `count` of `cards`, keeping them in order
+fn combinations(cards: &[CityIdx], count: usize) -> Vec<Vec<CityIdx>> {
+    if count == 0 {
+        return vec![Vec::new()];
+    }
+    if cards.len() < count {
+        return Vec::new();
+    }
+
+    let mut picks: Vec<Vec<CityIdx>> = combinations(&cards[1..], count - 1).into_iter()
+        .map(|rest| std::iter::once(cards[0]).chain(rest).collect())
+        .collect();
+    picks.extend(combinations(&cards[1..], count));
+    picks
+}
+
+impl Game {
+    // Every action `player` can take in the current state of the game
+    pub fn legal_actions(&self, player: PlayerId) -> Vec<Action> {
+        let mut candidates = Vec::new();
+
+        let pawns: Vec<Option<PlayerId>> = std::iter::once(None)
+            .chain((0..self.players.len()).filter(|p| *p != player).map(Some))
+            .collect();
+
+        for city in 0..self.world.cities.len() {
+            for pawn in pawns.iter() {
+                candidates.push(Action::Drive(city, *pawn));
+                candidates.push(Action::Direct(city, *pawn));
+                candidates.push(Action::Charter(city, *pawn));
+                candidates.push(Action::Shuttle(city, *pawn));
+            }
+
+            candidates.push(Action::BuildResearchCenter(city, None));
+            for station in self.world.research_stations.iter() {
+                candidates.push(Action::BuildResearchCenter(city, Some(*station)));
+            }
+
+            for kind in self.world.diseases.iter() {
+                candidates.push(Action::TreatDisease(city, *kind));
+            }
+        }
+
+        for pawn in 0..self.players.len() {
+            for to in 0..self.players.len() {
+                candidates.push(Action::MovePawnToPawn { pawn, to });
+            }
+        }
+
+
+        for card in self.world.play_deck.discarded() {
+            if let PlayCard::Event(event) = card {
+                candidates.push(Action::RetrieveEvent(event));
+            }
+        }
+
+        if let Some(p) = self.players.get(player) {
+            for kind in self.world.diseases.iter() {
+                let cards = p.city_cards(&self.world, *kind);
+                for chosen in combinations(&cards, self.cure_cards(player, *kind)) {
+                    candidates.push(Action::DiscoverCure(*kind, chosen));
+                }
+            }
+
+            for (other, o) in self.players.iter().enumerate() {
+                for card in p.cards.iter() {
+                    if let PlayCard::City(city) = card {
+                        candidates.push(Action::ShareKnowledge { with: other, card: *city, direction: ShareDirection::Give });
+                    }
+                }
+                for card in o.cards.iter() {
+                    if let PlayCard::City(city) = card {
+                        candidates.push(Action::ShareKnowledge { with: other, card: *city, direction: ShareDirection::Take });
+                    }
+                }
+            }
+        }
+
+        candidates.into_iter().filter(|action| self.check_action(player, action).is_ok()).collect()
+    }
+
+    pub fn check_action(&self, player: PlayerId, action: &Action) -> Result<(), ActionError> {
+        if self.is_over() {
+            return Err(ActionError::GameOver);
+        }
+
+        if player >= self.players.len() {
+            return Err(ActionError::InvalidPlayer(player));
+        }
+
+        if player != self.current_player {
+            return Err(ActionError::NotYourTurn(player));
+        }
+
+        match self.turn {
+        Turn::Action(left) if left > 0 => {},
+        _ => return Err(ActionError::NoActionsLeft),
+        }
+
+        if let Some(pending) = &self.pending {
+            return Err(ActionError::AwaitingConsent(pending.target));
+        }
+
+        let p = &self.players[player];
+        let curr_city = p.current_city;
+
+        let check_city = |city: CityIdx| {
+            if city >= self.world.cities.len() {
+                Err(ActionError::InvalidCity(city))
+            } else {
+                Ok(())
+            }
+        };
+        let check_card = |city: CityIdx| {
+            if p.cards.contains(&PlayCard::City(city)) {
+                Ok(())
+            } else {
+                Err(ActionError::MissingCard(city))
+            }
+        };
+        // City the moved pawn is in, after checking `player` may move it
+        let check_move = |pawn: PlayerId, city: CityIdx| {
+            if pawn >= self.players.len() {
+                return Err(ActionError::InvalidPlayer(pawn));
+            }
+            if pawn != player && !p.profession.moves_other_pawns() {
+                return Err(ActionError::NotAllowed(p.profession));
+            }
+
+            check_city(city)?;
+
+            let from = self.players[pawn].current_city;
+            if city == from {
+                Err(ActionError::AlreadyThere(city))
+            } else {
+                Ok(from)
+            }
+        };
+
+        match action {
+        Action::Drive(city, pawn) => {
+            let from = check_move(pawn.unwrap_or(player), *city)?;
+            if !self.world.map.connected(from, *city) {
+                return Err(ActionError::NotConnected(from, *city));
+            }
+        },
+        Action::Direct(city, pawn) => {
+            check_move(pawn.unwrap_or(player), *city)?;
+            check_card(*city)?;
+        },
+        Action::Charter(city, pawn) => {
+            let from = check_move(pawn.unwrap_or(player), *city)?;
+            check_card(from)?;
+        },
+        Action::Shuttle(city, pawn) => {
+            let from = check_move(pawn.unwrap_or(player), *city)?;
+            if !self.world.has_research_station(from) {
+                return Err(ActionError::NoResearchStation(from));
+            }
+            if !self.world.has_research_station(*city) {
+                return Err(ActionError::NoResearchStation(*city));
+            }
+        },
+        Action::MovePawnToPawn { pawn, to } => {
+            if *to >= self.players.len() || to == pawn {
+                return Err(ActionError::InvalidPlayer(*to));
+            }
+            if !p.profession.moves_other_pawns() {
+                return Err(ActionError::NotAllowed(p.profession));
+            }
+            check_move(*pawn, self.players[*to].current_city)?;
+        },
+        Action::TreatDisease(city, kind) => {
+            check_city(*city)?;
+            if *city != curr_city {
+                return Err(ActionError::NotInCity(*city));
+            }
+            if self.world.cities[*city].cubes(*kind) == 0 {
+                return Err(ActionError::NoCubes(*city, *kind));
+            }
+        },
+        Action::BuildResearchCenter(city, relocate) => {
+            check_city(*city)?;
+            if *city != curr_city {
+                return Err(ActionError::NotInCity(*city));
+            }
+            self.check_station(*city, *relocate)?;
+
+            if !p.profession.builds_without_card() {
+                check_card(*city)?;
+            }
+        },
+        Action::DiscoverCure(kind, cards) => {
+            if !self.world.has_research_station(curr_city) {
+                return Err(ActionError::NoResearchStation(curr_city));
+            }
+            if !self.world.diseases.contains(kind) {
+                return Err(ActionError::NotInPlay(*kind));
+            }
+            if self.world.cure_status(*kind) != CureStatus::Active {
+                return Err(ActionError::AlreadyCured(*kind));
+            }
+            if cards.len() != self.cure_cards(player, *kind) {
+                return Err(ActionError::NotEnoughCards(*kind, self.cure_cards(player, *kind)));
+            }
+            for (i, city) in cards.iter().enumerate() {
+                check_city(*city)?;
+                // Every card is in hand once
+                if cards[..i].contains(city) {
+                    return Err(ActionError::MissingCard(*city));
+                }
+                check_card(*city)?;
+                if !kind.cured_by(self.world.cities[*city].kind) {
+                    return Err(ActionError::NotCuredBy(*city, *kind));
+                }
+            }
+        },
+        Action::ShareKnowledge { with, card, direction } => {
+            if *with >= self.players.len() || *with == player {
+                return Err(ActionError::InvalidPlayer(*with));
+            }
+
+            let o = &self.players[*with];
+            if o.current_city != curr_city {
+                return Err(ActionError::NotInSameCity(*with));
+            }
+
+            let giver = match direction {
+            ShareDirection::Give => p,
+            ShareDirection::Take => o,
+            };
+            if *card != curr_city && !giver.profession.shares_any_card() {
+                return Err(ActionError::CannotShare(*card));
+            }
+            if !giver.cards.contains(&PlayCard::City(*card)) {
+                return Err(ActionError::MissingCard(*card));
+            }
+        },
+        Action::RetrieveEvent(event) => {
+            if !p.profession.stores_events() {
+                return Err(ActionError::NotAllowed(p.profession));
+            }
+            if p.stored_event.is_some() {
+                return Err(ActionError::EventAlreadyStored);
+            }
+            if !self.world.play_deck.in_discard(&PlayCard::Event(event.clone())) {
+                return Err(ActionError::NotInDiscard(event.clone()));
+            }
+        },
+        }
+
+        Ok(())
+    }
+
+    // Validate and perform `action` for `player`, spending one of their actions.
+    // Actions involving another player are put on hold until they agree.
+    pub fn apply_action(&mut self, player: PlayerId, action: Action) -> Result<ActionOutcome, ActionError> {
+        self.check_action(player, &action)?;
+
+        if let Some(target) = self.consent_needed(player, &action) {
+            self.pending = Some(PendingAction { player, target, request: Request::Action(action) });
+            return Ok(ActionOutcome::AwaitingConsent(target));
+        }
+
+        Ok(self.perform_action(player, action))
+    }
+
+    // Accept or decline the pending action `player` was asked about
+    pub fn answer_consent(&mut self, player: PlayerId, accept: bool) -> Result<ActionOutcome, ActionError> {
+        match &self.pending {
+        Some(pending) if pending.target == player => {},
+        _ => return Err(ActionError::NothingToAnswer(player)),
+        }
+
+        let pending = self.pending.take().unwrap();
+        if !accept {
+            return Ok(ActionOutcome::Declined(player));
+        }
+
+        match pending.request {
+        Request::Action(action) => {
+            self.check_action(pending.player, &action)?;
+            Ok(self.perform_action(pending.player, action))
+        },
+        Request::Event(event) => {
+            self.check_event(pending.player, &event)?;
+            Ok(self.perform_event(pending.player, event))
+        },
+        }
+    }
+
+    fn consent_needed(&self, player: PlayerId, action: &Action) -> Option<PlayerId> {
+        match action {
+        Action::ShareKnowledge { with, .. } => Some(*with),
+        // Moving another pawn using a card needs the owner's permission
+        Action::Direct(_, Some(pawn)) | Action::Charter(_, Some(pawn)) if *pawn != player => Some(*pawn),
+        _ => None,
+        }
+    }
+
+    fn perform_action(&mut self, player: PlayerId, action: Action) -> ActionOutcome {
+        self.save_undo();
+        self.emit(GameEvent::ActionTaken { player, action: action.clone() });
+
+        let cures = self.world.cures;
+        let outcome = match action {
+        Action::Drive(city, pawn) | Action::Shuttle(city, pawn) => {
+            self.move_pawn(pawn.unwrap_or(player), city)
+        },
+        Action::Direct(city, pawn) => {
+            self.discard_card(player, &PlayCard::City(city));
+            self.move_pawn(pawn.unwrap_or(player), city)
+        },
+        Action::Charter(city, pawn) => {
+            let pawn = pawn.unwrap_or(player);
+            self.discard_card(player, &PlayCard::City(self.players[pawn].current_city));
+            self.move_pawn(pawn, city)
+        },
+        Action::MovePawnToPawn { pawn, to } => {
+            self.move_pawn(pawn, self.players[to].current_city)
+        },
+        Action::TreatDisease(city, kind) => {
+            let count = if self.world.cure_status(kind) == CureStatus::Active && !self.players[player].profession.treats_all() {
+                1
+            } else {
+                self.world.cities[city].cubes(kind)
+            };
+            let removed = self.world.remove_cubes(city, kind, count);
+            ActionOutcome::Treated { city, kind, removed }
+        },
+        Action::BuildResearchCenter(city, relocated_from) => {
+            if !self.players[player].profession.builds_without_card() {
+                self.discard_card(player, &PlayCard::City(city));
+            }
+            self.world.build_research_station(city, relocated_from);
+            ActionOutcome::Built { city, relocated_from }
+        },
+        Action::DiscoverCure(kind, cards) => {
+            for city in cards {
+                self.discard_card(player, &PlayCard::City(city));
+            }
+
+            self.world.cure(kind);
+            self.on_cure();
+            ActionOutcome::Cured(kind)
+        },
+        Action::ShareKnowledge { with, card, direction } => {
+            let (from, to) = match direction {
+            ShareDirection::Give => (player, with),
+            ShareDirection::Take => (with, player),
+            };
+
+            self.players[from].remove_card(&PlayCard::City(card));
+            self.players[to].add_card(PlayCard::City(card));
+            ActionOutcome::Shared { from, to, card }
+        },
+        Action::RetrieveEvent(event) => {
+            self.world.play_deck.take_from_discard(&PlayCard::Event(event.clone()));
+            self.players[player].stored_event = Some(event.clone());
+            ActionOutcome::EventStored(event)
+        },
+        };
+
+        match &outcome {
+        ActionOutcome::Treated { city, kind, removed } => {
+            self.emit(GameEvent::CubesTreated { city: *city, kind: *kind, removed: *removed });
+        },
+        ActionOutcome::Built { city, relocated_from } => {
+            self.emit(GameEvent::ResearchStationBuilt { city: *city, relocated_from: *relocated_from });
+        },
+        ActionOutcome::Cured(kind) => self.emit(GameEvent::CureDiscovered(*kind)),
+        ActionOutcome::Shared { from, to, card } => {
+            self.emit(GameEvent::CardShared { from: *from, to: *to, card: *card });
+        },
+        ActionOutcome::EventStored(event) => {
+            self.emit(GameEvent::EventStored { player, event: event.clone() });
+        },
+        // Moves are reported by `move_pawn`
+        _ => {},
+        }
+        self.emit_eradications(cures);
+
+        if let Some(turn) = self.turn.play_action() {
+            self.turn = turn;
+        }
+
+        if let ActionOutcome::Shared { to, .. } = outcome {
+            self.check_hand_limit(to);
+        }
+
+        if self.world.all_cured() {
+            self.end_game(GameOutcome::Victory);
+        }
+
+        outcome
+    }
+
+    pub fn describe_action(&self, action: &Action) -> String {
+        let name = |city: &CityIdx| &self.world.cities[*city].name;
+        let pawn_name = |pawn: &Option<PlayerId>| match pawn {
+            Some(pawn) => format!("{} ", self.players[*pawn].name),
+            None => String::new(),
+        };
+
+        match action {
+        Action::Drive(city, pawn) => format!("Drive {}to {}", pawn_name(pawn), name(city)),
+        Action::Direct(city, pawn) => format!("Direct flight {}to {}", pawn_name(pawn), name(city)),
+        Action::Charter(city, pawn) => format!("Charter flight {}to {}", pawn_name(pawn), name(city)),
+        Action::Shuttle(city, pawn) => format!("Shuttle flight {}to {}", pawn_name(pawn), name(city)),
+        Action::MovePawnToPawn { pawn, to } => {
+            format!("Move {} to {}", self.players[*pawn].name, self.players[*to].name)
+        },
+        Action::TreatDisease(city, kind) => format!("Treat {:?} in {}", kind, name(city)),
+        Action::BuildResearchCenter(city, None) => format!("Build research center in {}", name(city)),
+        Action::BuildResearchCenter(city, Some(from)) => format!("Move research center from {} to {}", name(from), name(city)),
+        Action::DiscoverCure(kind, cards) => {
+            let cards: Vec<&str> = cards.iter().map(|city| name(city).as_str()).collect();
+            format!("Discover cure for {:?} with {}", kind, cards.join(", "))
+        },
+        Action::RetrieveEvent(event) => format!("Retrieve {:?} from the discard pile", event),
+        Action::ShareKnowledge { with, card, direction: ShareDirection::Give } => {
+            format!("Give {} to {}", name(card), self.players[*with].name)
+        },
+        Action::ShareKnowledge { with, card, direction: ShareDirection::Take } => {
+            format!("Take {} from {}", name(card), self.players[*with].name)
+        },
+        }
+    }
+
+    pub fn describe_request(&self, request: &Request) -> String {
+        match request {
+        Request::Action(action) => self.describe_action(action),
+        Request::Event(Event::Airlift(pawn, city)) => {
+            format!("Airlift {} to {}", self.players[*pawn].name, self.world.cities[*city].name)
+        },
+        Request::Event(event) => format!("Play {}", event.card().title),
+        }
+    }
+
+    pub fn describe_card(&self, card: &PlayCard) -> String {
+        match card {
+        PlayCard::City(city) => format!("{} ({:?})", self.world.cities[*city].name, self.world.cities[*city].kind),
+        PlayCard::Event(event) => format!("{}: {}", event.card().title, event.card().effect),
+        PlayCard::Epidemic => "Epidemic".to_string(),
+        PlayCard::VirulentEpidemic(effect) => format!("Epidemic, {}: {}", effect.title(), effect.effect()),
+        }
+    }
+
+    // Name of the infection card `card`
+    pub fn describe_infection_card(&self, card: CardIdx) -> String {
+        match &self.world.disease_deck.cards[card] {
+        DiseaseCard::City(city) => self.world.cities[*city].name.clone(),
+        DiseaseCard::Mutation(mutation) => mutation.title().to_string(),
+        }
+    }
+
+    // Whether a research station may be built in `city`, moving the one in
+    // `relocate` once all of them are on the board
+    pub(crate) fn check_station(&self, city: CityIdx, relocate: Option<CityIdx>) -> Result<(), ActionError> {
+        if self.world.has_research_station(city) {
+            return Err(ActionError::ResearchStationExists(city));
+        }
+
+        let stations_left = self.world.research_stations.len() < MAX_RESEARCH_STATIONS;
+        match relocate {
+        None if !stations_left => Err(ActionError::NoResearchStationsLeft),
+        Some(from) if stations_left || !self.world.has_research_station(from) => {
+            Err(ActionError::InvalidRelocation(from))
+        },
+        _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn move_pawn(&mut self, pawn: PlayerId, city: CityIdx) -> ActionOutcome {
+        let from = self.players[pawn].current_city;
+        self.players[pawn].current_city = city;
+        self.emit(GameEvent::PawnMoved { pawn, from, to: city });
+        self.on_pawn_moved(pawn);
+
+        ActionOutcome::Moved { pawn, from, to: city }
+    }
+
+    // Pause the turn until `player` is back within the hand limit
+    pub(crate) fn check_hand_limit(&mut self, player: PlayerId) {
+        if self.players[player].cards_over_limit() > 0 {
+            self.turn = Turn::Discard(player, Box::new(self.turn.clone()));
+        }
+    }
+
+    // Discard `cards` from the hand of `player`, who is over the hand limit
+    pub fn discard_cards(&mut self, player: PlayerId, cards: Vec<PlayCard>) -> Result<(), ActionError> {
+        match &self.turn {
+        Turn::Discard(p, _) if *p == player => {},
+        _ => return Err(ActionError::NotDiscarding(player)),
+        }
+
+        let over_limit = self.players[player].cards_over_limit();
+        if cards.len() != over_limit {
+            return Err(ActionError::MustDiscard(over_limit));
+        }
+
+        let mut hand = self.players[player].cards.clone();
+        for card in cards.iter() {
+            match hand.iter().position(|c| c == card) {
+            Some(idx) => { hand.remove(idx); },
+            None => return Err(ActionError::NotInHand(card.clone())),
+            }
+        }
+
+        for card in cards.iter() {
+            self.discard_card(player, card);
+        }
+        self.emit(GameEvent::CardsDiscarded { player, cards });
+
+        if let Some(turn) = self.turn.discard() {
+            self.turn = turn;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn discard_card(&mut self, player: PlayerId, card: &PlayCard) {
+        if self.players[player].remove_card(card) {
+            self.world.play_deck.discard(card);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, GameOutcome, cards::{Action, PlayCard, ShareDirection}, city::CityIdx, disease::{DiseaseKind, CureStatus}, log::GameEvent, player::{Profession, HAND_LIMIT}, turn::Turn};
+
+    use super::{ActionError, ActionOutcome};
+
+    // A game where the first player is about to act, both players in the
+    // start city with empty hands and no role
+    fn acting_game() -> Game {
+        let mut game = new_game(&shipped_config(), 8, 2);
+        for player in game.players.iter_mut() {
+            player.profession = Profession::None;
+            player.cards.clear();
+        }
+        game.current_player = 0;
+        game.turn = Turn::Action(4);
+
+        game
+    }
+
+    fn start(game: &Game) -> CityIdx {
+        game.world.start_city
+    }
+
+    fn neighbour(game: &Game) -> CityIdx {
+        game.world.map.neighbours(start(game)).next().unwrap()
+    }
+
+    // A city neither connected to the start city nor the start city itself
+    fn far_away(game: &Game) -> CityIdx {
+        let from = start(game);
+        (0..game.world.cities.len()).find(|c| *c != from && !game.world.map.connected(from, *c)).unwrap()
+    }
+
+    fn cards_of(game: &Game, kind: DiseaseKind, count: usize) -> Vec<PlayCard> {
+        game.world.cities.iter().filter(|city| city.kind == kind).take(count).map(|city| PlayCard::City(city.index)).collect()
+    }
+
+    fn cities_of(cards: &[PlayCard]) -> Vec<CityIdx> {
+        cards.iter().filter_map(|card| match card {
+            PlayCard::City(city) => Some(*city),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn actions_of_other_players_are_rejected() {
+        let mut game = acting_game();
+        let to = neighbour(&game);
+
+        assert_eq!(game.apply_action(1, Action::Drive(to, None)), Err(ActionError::NotYourTurn(1)));
+        assert_eq!(game.apply_action(7, Action::Drive(to, None)), Err(ActionError::InvalidPlayer(7)));
+        assert_eq!(game.players[1].current_city, start(&game));
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut game = acting_game();
+        let (from, far) = (start(&game), far_away(&game));
+        let cities = game.world.cities.len();
+
+        let mut check = |action, error| assert_eq!(game.apply_action(0, action), Err(error));
+        check(Action::Drive(far, None), ActionError::NotConnected(from, far));
+        check(Action::Drive(from, None), ActionError::AlreadyThere(from));
+        check(Action::Drive(cities, None), ActionError::InvalidCity(cities));
+        check(Action::Direct(far, None), ActionError::MissingCard(far));
+        check(Action::Charter(far, None), ActionError::MissingCard(from));
+        check(Action::Shuttle(far, None), ActionError::NoResearchStation(far));
+        check(Action::Drive(far, Some(1)), ActionError::NotAllowed(Profession::None));
+        check(Action::MovePawnToPawn { pawn: 1, to: 0 }, ActionError::NotAllowed(Profession::None));
+
+        assert!(matches!(game.turn, Turn::Action(4)));
+        assert!(game.log.iter().all(|event| !matches!(event, GameEvent::ActionTaken { .. })));
+    }
+
+    #[test]
+    fn illegal_treat_build_and_cure_are_rejected() {
+        let mut game = acting_game();
+        let (from, far) = (start(&game), far_away(&game));
+        game.world.cities[from].cubes = Default::default();
+
+        let check = |game: &mut Game, action, error| assert_eq!(game.apply_action(0, action), Err(error));
+        check(&mut game, Action::TreatDisease(from, DiseaseKind::Red), ActionError::NoCubes(from, DiseaseKind::Red));
+        check(&mut game, Action::TreatDisease(far, DiseaseKind::Red), ActionError::NotInCity(far));
+        check(&mut game, Action::BuildResearchCenter(from, None), ActionError::ResearchStationExists(from));
+        check(&mut game, Action::DiscoverCure(DiseaseKind::Blue, Vec::new()), ActionError::NotEnoughCards(DiseaseKind::Blue, 5));
+        check(&mut game, Action::DiscoverCure(DiseaseKind::Purple, Vec::new()), ActionError::NotInPlay(DiseaseKind::Purple));
+
+        let to = neighbour(&game);
+        game.apply_action(0, Action::Drive(to, None)).unwrap();
+        check(&mut game, Action::DiscoverCure(DiseaseKind::Blue, Vec::new()), ActionError::NoResearchStation(to));
+        check(&mut game, Action::BuildResearchCenter(to, None), ActionError::MissingCard(to));
+    }
+
+    #[test]
+    fn fifth_action_is_rejected() {
+        let mut game = acting_game();
+        let (from, to) = (start(&game), neighbour(&game));
+
+        for (i, city) in [to, from, to, from].into_iter().enumerate() {
+            assert!(matches!(game.turn, Turn::Action(left) if left as usize == 4 - i));
+            let from = game.players[0].current_city;
+            assert_eq!(game.apply_action(0, Action::Drive(city, None)), Ok(ActionOutcome::Moved { pawn: 0, from, to: city }));
+        }
+
+        assert!(matches!(game.turn, Turn::Draw(2)));
+        assert_eq!(game.apply_action(0, Action::Drive(to, None)), Err(ActionError::NoActionsLeft));
+    }
+
+    #[test]
+    fn treat_removes_one_cube_or_all_once_cured() {
+        let mut game = acting_game();
+        let city = start(&game);
+        game.world.cities[city].cubes = Default::default();
+        game.disease_city(city, DiseaseKind::Blue, 3);
+
+        let outcome = game.apply_action(0, Action::TreatDisease(city, DiseaseKind::Blue));
+        assert_eq!(outcome, Ok(ActionOutcome::Treated { city, kind: DiseaseKind::Blue, removed: 1 }));
+
+        game.world.cures[DiseaseKind::Blue.idx()] = CureStatus::Cured;
+        let outcome = game.apply_action(0, Action::TreatDisease(city, DiseaseKind::Blue));
+        assert_eq!(outcome, Ok(ActionOutcome::Treated { city, kind: DiseaseKind::Blue, removed: 2 }));
+        assert_eq!(game.world.cities[city].cubes(DiseaseKind::Blue), 0);
+    }
+
+    #[test]
+    fn cure_discards_the_cards() {
+        let mut game = acting_game();
+        let cards = cards_of(&game, DiseaseKind::Blue, 5);
+        game.players[0].cards = cards.clone();
+
+        assert_eq!(game.apply_action(0, Action::DiscoverCure(DiseaseKind::Blue, cities_of(&cards))), Ok(ActionOutcome::Cured(DiseaseKind::Blue)));
+
+        assert!(game.players[0].cards.is_empty());
+        assert_ne!(game.world.cure_status(DiseaseKind::Blue), CureStatus::Active);
+        assert!(cards.iter().all(|card| game.world.play_deck.in_discard(card)));
+        assert!(game.log.contains(&GameEvent::CureDiscovered(DiseaseKind::Blue)));
+        assert_eq!(game.apply_action(0, Action::DiscoverCure(DiseaseKind::Blue, cities_of(&cards))), Err(ActionError::AlreadyCured(DiseaseKind::Blue)));
+    }
+
+    #[test]
+    fn cure_discards_only_the_chosen_cards() {
+        let mut game = acting_game();
+        let hand = cards_of(&game, DiseaseKind::Blue, 6);
+        game.players[0].cards = hand.clone();
+        let chosen = cities_of(&hand[1..]);
+        let red = cities_of(&cards_of(&game, DiseaseKind::Red, 1))[0];
+        let missing = cities_of(&cards_of(&game, DiseaseKind::Blue, 7))[6];
+
+        let check = |game: &mut Game, cards: Vec<CityIdx>, error| assert_eq!(game.apply_action(0, Action::DiscoverCure(DiseaseKind::Blue, cards)), Err(error));
+        check(&mut game, chosen[1..].to_vec(), ActionError::NotEnoughCards(DiseaseKind::Blue, 5));
+        check(&mut game, [&chosen[1..], &chosen[1..2]].concat(), ActionError::MissingCard(chosen[1]));
+        game.players[0].cards.push(PlayCard::City(red));
+        check(&mut game, [&chosen[1..], &[red]].concat(), ActionError::NotCuredBy(red, DiseaseKind::Blue));
+        check(&mut game, [&chosen[1..], &[missing]].concat(), ActionError::MissingCard(missing));
+
+        // Every pick of 5 out of the 6 cards is offered
+        let cures = game.legal_actions(0).into_iter().filter(|action| matches!(action, Action::DiscoverCure(..))).count();
+        assert_eq!(cures, 6);
+
+        game.apply_action(0, Action::DiscoverCure(DiseaseKind::Blue, chosen)).unwrap();
+        assert_eq!(game.players[0].cards, vec![hand[0].clone(), PlayCard::City(red)]);
+    }
+
+    #[test]
+    fn last_cure_wins_the_game() {
+        let mut game = acting_game();
+        for kind in [DiseaseKind::Red, DiseaseKind::Yellow, DiseaseKind::Black] {
+            game.world.cures[kind.idx()] = CureStatus::Cured;
+        }
+        game.players[0].cards = cards_of(&game, DiseaseKind::Blue, 5);
+
+        game.apply_action(0, Action::DiscoverCure(DiseaseKind::Blue, cities_of(&game.players[0].cards))).unwrap();
+
+        assert_eq!(game.outcome, Some(GameOutcome::Victory));
+        assert_eq!(game.apply_action(0, Action::Drive(neighbour(&game), None)), Err(ActionError::GameOver));
+    }
+
+    #[test]
+    fn share_waits_for_consent() {
+        let mut game = acting_game();
+        let card = start(&game);
+        game.players[0].cards = vec![PlayCard::City(card)];
+        let give = Action::ShareKnowledge { with: 1, card, direction: ShareDirection::Give };
+
+        assert_eq!(game.apply_action(0, give.clone()), Ok(ActionOutcome::AwaitingConsent(1)));
+        assert_eq!(game.apply_action(0, Action::Drive(neighbour(&game), None)), Err(ActionError::AwaitingConsent(1)));
+        assert_eq!(game.answer_consent(1, false), Ok(ActionOutcome::Declined(1)));
+        assert_eq!(game.players[0].cards, vec![PlayCard::City(card)]);
+        assert!(matches!(game.turn, Turn::Action(4)));
+
+        game.apply_action(0, give).unwrap();
+        assert_eq!(game.answer_consent(1, true), Ok(ActionOutcome::Shared { from: 0, to: 1, card }));
+        assert_eq!(game.players[1].cards, vec![PlayCard::City(card)]);
+        assert!(matches!(game.turn, Turn::Action(3)));
+    }
+
+    #[test]
+    fn share_only_the_current_city_card() {
+        let mut game = acting_game();
+        let far = far_away(&game);
+        game.players[0].cards = vec![PlayCard::City(far)];
+
+        let give = Action::ShareKnowledge { with: 1, card: far, direction: ShareDirection::Give };
+        assert_eq!(game.apply_action(0, give.clone()), Err(ActionError::CannotShare(far)));
+
+        game.players[0].profession = Profession::Researcher;
+        assert_eq!(game.apply_action(0, give), Ok(ActionOutcome::AwaitingConsent(1)));
+    }
+
+    #[test]
+    fn hand_limit_pauses_the_turn_until_discarded() {
+        let mut game = acting_game();
+        let card = start(&game);
+        game.players[0].cards = vec![PlayCard::City(card)];
+        let hand: Vec<PlayCard> = (0..game.world.cities.len()).filter(|c| *c != card).take(HAND_LIMIT).map(PlayCard::City).collect();
+        game.players[1].cards = hand.clone();
+
+        game.apply_action(0, Action::ShareKnowledge { with: 1, card, direction: ShareDirection::Give }).unwrap();
+        game.answer_consent(1, true).unwrap();
+        assert!(matches!(&game.turn, Turn::Discard(1, resume) if matches!(**resume, Turn::Action(3))));
+        assert_eq!(game.apply_action(0, Action::Drive(neighbour(&game), None)), Err(ActionError::NoActionsLeft));
+
+        assert_eq!(game.discard_cards(0, vec![hand[0].clone()]), Err(ActionError::NotDiscarding(0)));
+        assert_eq!(game.discard_cards(1, Vec::new()), Err(ActionError::MustDiscard(1)));
+        let missing = PlayCard::City(game.world.cities.len() - 1);
+        assert!(!game.players[1].cards.contains(&missing));
+        assert_eq!(game.discard_cards(1, vec![missing.clone()]), Err(ActionError::NotInHand(missing)));
+
+        assert_eq!(game.discard_cards(1, vec![hand[0].clone()]), Ok(()));
+        assert_eq!(game.players[1].cards.len(), HAND_LIMIT);
+        assert!(game.world.play_deck.in_discard(&hand[0]));
+        assert!(matches!(game.turn, Turn::Action(3)));
+    }
+
+    // An acting game without cubes on the board, so that only moves and
+    // shares are legal
+    fn clean_game() -> Game {
+        let mut game = acting_game();
+        for city in game.world.cities.iter_mut() {
+            city.cubes = Default::default();
+        }
+
+        game
+    }
+
+    fn assert_same_actions(actions: Vec<Action>, expected: Vec<Action>) {
+        assert_eq!(actions.len(), expected.len(), "{actions:?} != {expected:?}");
+        assert!(expected.iter().all(|action| actions.contains(action)), "{actions:?} != {expected:?}");
+    }
+
+    fn drives(game: &Game, from: CityIdx, pawn: Option<usize>) -> Vec<Action> {
+        game.world.map.neighbours(from).map(|city| Action::Drive(city, pawn)).collect()
+    }
+
+    #[test]
+    fn legal_actions_shuttle_between_stations() {
+        let mut game = clean_game();
+        let (from, far) = (start(&game), far_away(&game));
+        game.world.research_stations = vec![from, far];
+
+        let mut expected = drives(&game, from, None);
+        expected.push(Action::Shuttle(far, None));
+        assert_same_actions(game.legal_actions(0), expected);
+        assert!(game.legal_actions(1).is_empty());
+    }
+
+    #[test]
+    fn legal_actions_of_the_dispatcher_move_other_pawns() {
+        let mut game = clean_game();
+        let (from, to) = (start(&game), neighbour(&game));
+        game.players[0].profession = Profession::Dispatcher;
+        game.players[1].current_city = to;
+
+        let mut expected = drives(&game, from, None);
+        expected.extend(drives(&game, to, Some(1)));
+        expected.push(Action::MovePawnToPawn { pawn: 0, to: 1 });
+        expected.push(Action::MovePawnToPawn { pawn: 1, to: 0 });
+        assert_same_actions(game.legal_actions(0), expected);
+    }
+
+    #[test]
+    fn legal_actions_give_or_take_the_city_card() {
+        let mut game = clean_game();
+        let (card, next, far) = (start(&game), neighbour(&game), far_away(&game));
+        let shares = |game: &Game| game.legal_actions(0).into_iter().filter(|action| matches!(action, Action::ShareKnowledge { .. })).collect();
+
+        game.players[0].cards = vec![PlayCard::City(card)];
+        game.players[1].cards = vec![PlayCard::City(far)];
+        assert_same_actions(shares(&game), vec![Action::ShareKnowledge { with: 1, card, direction: ShareDirection::Give }]);
+
+        game.players[0].cards = vec![PlayCard::City(far)];
+        game.players[1].cards = vec![PlayCard::City(card)];
+        assert_same_actions(shares(&game), vec![Action::ShareKnowledge { with: 1, card, direction: ShareDirection::Take }]);
+
+        // A Researcher gives any card, so it can be taken from them too
+        game.players[1].profession = Profession::Researcher;
+        game.players[1].cards.push(PlayCard::City(next));
+        assert_same_actions(shares(&game), vec![
+            Action::ShareKnowledge { with: 1, card, direction: ShareDirection::Take },
+            Action::ShareKnowledge { with: 1, card: next, direction: ShareDirection::Take },
+        ]);
+
+        game.players[1].current_city = far;
+        assert_same_actions(shares(&game), Vec::new());
+    }
+}
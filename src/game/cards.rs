@@ -1,38 +1,69 @@
+use rand::{seq::SliceRandom, Rng};
 use serde::{Serialize, Deserialize};
 
-use super::{city::CityIdx, disease::DiseaseKind, player::PlayerId};
+use super::{city::CityIdx, disease::DiseaseKind, player::PlayerId, challenge::{VirulentEffect, Mutation}};
 
-use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Default, Clone)]
+#[derive(PartialEq, Clone)]
 #[derive(Debug, Serialize, Deserialize)]
-pub struct DiseaseCard(pub CityIdx);
+pub enum DiseaseCard {
+    City(CityIdx),
+    // Shuffled into the infection deck by the Mutation challenge
+    Mutation(Mutation),
+}
 
 #[derive(PartialEq, Clone)]
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Action {
-    BuildResearchCenter(CityIdx),
-    TreatDisease(CityIdx),
-    DiscoverCure(DiseaseKind),
-    ShareKnowledge(PlayerId),
-
-    Drive(CityIdx),
-    Direct(CityIdx),
-    Charter(CityIdx),
-    Shuttle(CityIdx),
+    // City to build in and, when all stations are in use, the station to move
+    BuildResearchCenter(CityIdx, Option<CityIdx>),
+    TreatDisease(CityIdx, DiseaseKind),
+    // Disease to cure and the city cards discarded for it
+    DiscoverCure(DiseaseKind, Vec<CityIdx>),
+    // Contingency Planner takes an event card back from the discard pile
+    RetrieveEvent(Event),
+    ShareKnowledge { with: PlayerId, card: CityIdx, direction: ShareDirection },
+
+    // Moves carry the pawn to move when it is not the acting player's own
+    Drive(CityIdx, Option<PlayerId>),
+    Direct(CityIdx, Option<PlayerId>),
+    Charter(CityIdx, Option<PlayerId>),
+    Shuttle(CityIdx, Option<PlayerId>),
+    // Move `pawn` to the city of the pawn of `to`
+    MovePawnToPawn { pawn: PlayerId, to: PlayerId },
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ShareDirection {
+    Give,
+    Take,
 }
 
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize, EnumIter)]
 pub enum Event {
-    GovermentGrant,
-    Forecast,
-    Airlift(CityIdx),
+    // City to build in and, when all stations are in use, the station to move
+    GovermentGrant(CityIdx, Option<CityIdx>),
+    // New order of the top infection cards, top card first
+    Forecast(Vec<CardIdx>),
+    Airlift(PlayerId, CityIdx),
     OneQuietNight,
+    // Infection card to remove from the discard pile
     ResilientPopulation(CardIdx),
 }
 
+impl Event {
+    // Whether both events are played with the same card, whatever their targets
+    pub fn same_card(&self, other: &Event) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn card(&self) -> &'static EventCard {
+        EVENTS.iter().find(|card| card.kind.same_card(self)).unwrap()
+    }
+}
+
 #[derive(PartialEq, Clone, Serialize, Deserialize)]
 #[derive(Debug)]
 pub struct EventCard {
@@ -42,30 +73,31 @@ pub struct EventCard {
 }
 
 pub const NUM_EVENTS: usize = 5;
+pub const FORECAST_CARDS: usize = 6;
 pub static EVENTS: [EventCard; NUM_EVENTS] = [
     EventCard {
-        title: "GovermentGrand",
-        effect: "",
-        kind: Event::GovermentGrant,
+        title: "Government Grant",
+        effect: "Add 1 research station to any city (no City card needed).",
+        kind: Event::GovermentGrant(0, None),
     },
     EventCard {
         title: "Forecast",
-        effect: "",
-        kind: Event::Forecast,
+        effect: "Draw, look at, and rearrange the top 6 cards of the Infection Deck. Put them back on top.",
+        kind: Event::Forecast(Vec::new()),
     },
     EventCard {
         title: "Airlift",
-        effect: "",
-        kind: Event::Airlift(0),
+        effect: "Move any 1 pawn to any city.",
+        kind: Event::Airlift(0, 0),
     },
     EventCard {
-        title: "OneQuietNight",
-        effect: "",
+        title: "One Quiet Night",
+        effect: "Skip the next Infect Cities step (do not flip over any Infection cards).",
         kind: Event::OneQuietNight,
     },
     EventCard {
-        title: "ResilientPopulation",
-        effect: "",
+        title: "Resilient Population",
+        effect: "Remove any 1 card in the Infection Discard Pile from the game.",
         kind: Event::ResilientPopulation(0),
     },
 ];
@@ -77,11 +109,19 @@ pub enum PlayCard {
     Event(Event),
     #[default]
     Epidemic,
+    // Replaces the epidemic cards in the Virulent Strain challenge
+    VirulentEpidemic(VirulentEffect),
+}
+
+impl PlayCard {
+    pub fn is_epidemic(&self) -> bool {
+        matches!(self, PlayCard::Epidemic | PlayCard::VirulentEpidemic(_))
+    }
 }
 
 pub type CardIdx = usize;
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[derive(Debug)]
 pub struct Deck<T: Clone> {
     pub cards: Vec<T>,
@@ -89,6 +129,13 @@ pub struct Deck<T: Clone> {
     pub cards_discard: Vec<CardIdx>,
 }
 
+// Not derived, which would need a default card
+impl<T: Clone> Default for Deck<T> {
+    fn default() -> Self {
+        Self { cards: Vec::new(), cards_stack: Vec::new(), cards_discard: Vec::new() }
+    }
+}
+
 impl<T: Clone> Deck<T> {
     pub fn draw(&mut self) -> Option<T> {
         if let Some(card_idx) = self.cards_stack.pop() {
@@ -99,4 +146,129 @@ impl<T: Clone> Deck<T> {
             None
         }
     }
-}
\ No newline at end of file
+
+    // Take the top card without discarding it, e.g. into a player's hand
+    pub fn take(&mut self) -> Option<(CardIdx, T)> {
+        self.cards_stack.pop().map(|card_idx| (card_idx, self.cards[card_idx].clone()))
+    }
+
+    pub fn draw_bottom(&mut self) -> Option<T> {
+        if self.cards_stack.is_empty() {
+            return None;
+        }
+
+        let card_idx = self.cards_stack.remove(0);
+        self.cards_discard.push(card_idx);
+
+        Some(self.cards[card_idx].clone())
+    }
+
+    pub fn discard_at(&mut self, card_idx: CardIdx) {
+        self.cards_discard.push(card_idx);
+    }
+
+    // Only for cards which are unique in the deck, such as the ones in the
+    // players' hands. Copies of a card are discarded with `discard_at`.
+    pub fn discard(&mut self, card: &T) where T: PartialEq {
+        if let Some(card_idx) = self.cards.iter().position(|c| c == card) {
+            self.discard_at(card_idx);
+        }
+    }
+
+    pub fn discarded(&self) -> Vec<T> {
+        self.cards_discard.iter().map(|card_idx| self.cards[*card_idx].clone()).collect()
+    }
+
+    pub fn in_discard(&self, card: &T) -> bool where T: PartialEq {
+        self.cards_discard.iter().any(|card_idx| self.cards[*card_idx] == *card)
+    }
+
+    // Remove `card` from the discard pile, e.g. to take it out of the game
+    pub fn take_from_discard(&mut self, card: &T) -> bool where T: PartialEq {
+        if let Some(idx) = self.cards_discard.iter().position(|card_idx| self.cards[*card_idx] == *card) {
+            self.cards_discard.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Split the stack into one pile per card in `cards`, shuffle a card into
+    // each pile and stack the piles again, larger ones on top
+    pub fn shuffle_into_piles<R: Rng>(&mut self, cards: Vec<T>, rng: &mut R) {
+        let piles = cards.len();
+        if piles == 0 {
+            return;
+        }
+
+        let stack = std::mem::take(&mut self.cards_stack);
+        let size = stack.len() / piles;
+        let larger = stack.len() % piles;
+
+        // The stack is built bottom up, so the smaller piles go first
+        let mut rest = stack.as_slice();
+        for (i, card) in cards.into_iter().enumerate() {
+            let len = if i >= piles - larger { size + 1 } else { size };
+            let (pile, tail) = rest.split_at(len);
+            rest = tail;
+
+            let mut pile = pile.to_vec();
+            self.cards.push(card);
+            pile.push(self.cards.len() - 1);
+            pile.shuffle(rng);
+            self.cards_stack.append(&mut pile);
+        }
+    }
+
+    // Shuffle the discard pile and put it on top of the stack
+    pub fn reshuffle_discard<R: Rng>(&mut self, rng: &mut R) {
+        let mut discard = std::mem::take(&mut self.cards_discard);
+        discard.shuffle(rng);
+
+        self.cards_stack.append(&mut discard);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{Deck, PlayCard};
+
+    fn city_deck(cities: usize) -> Deck<PlayCard> {
+        Deck { cards: (0..cities).map(PlayCard::City).collect(), cards_stack: (0..cities).collect(), cards_discard: Vec::new() }
+    }
+
+    #[test]
+    fn every_epidemic_is_discarded_once() {
+        let mut deck = city_deck(20);
+        deck.shuffle_into_piles(vec![PlayCard::Epidemic; 4], &mut ChaCha8Rng::seed_from_u64(0));
+
+        while let Some((card_idx, card)) = deck.take() {
+            if card == PlayCard::Epidemic {
+                deck.discard_at(card_idx);
+            }
+        }
+
+        let mut discarded = deck.cards_discard.clone();
+        discarded.sort_unstable();
+        assert_eq!(discarded, vec![20, 21, 22, 23]);
+        assert_eq!(deck.discarded(), vec![PlayCard::Epidemic; 4]);
+    }
+
+    #[test]
+    fn epidemics_are_spread_over_the_piles() {
+        let mut deck = city_deck(22);
+        deck.shuffle_into_piles(vec![PlayCard::Epidemic; 4], &mut ChaCha8Rng::seed_from_u64(1));
+
+        // Piles of 6, 6, 5 and 5 cards with their epidemic, the larger ones on top
+        let mut top = Vec::new();
+        while let Some((_, card)) = deck.take() {
+            top.push(card);
+        }
+        for (pile, range) in [0..7, 7..14, 14..20, 20..26].into_iter().enumerate() {
+            let epidemics = top[range].iter().filter(|card| **card == PlayCard::Epidemic).count();
+            assert_eq!(epidemics, 1, "pile {pile}");
+        }
+    }
+}
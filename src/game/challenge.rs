@@ -0,0 +1,443 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use super::{Game, GameOutcome, cards::{PlayCard, DiseaseCard}, city::CityIdx, disease::{DiseaseKind, CureStatus}, log::GameEvent, player::PlayerId};
+
+// Cubes of the virulent strain put out of the game by Unacceptable Loss
+const UNACCEPTABLE_LOSS_CUBES: u8 = 4;
+// Purple cubes placed by The Mutation Spreads!
+const MUTATION_SPREAD_CUBES: u8 = 3;
+// Purple cubes in the supply unless the config says otherwise
+pub(crate) const PURPLE_CUBES: u8 = 12;
+
+// Optional rules making the game harder, chosen in the config
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub enum Challenge {
+    #[default]
+    None,
+    // The first epidemic makes a disease virulent, and every epidemic card
+    // has an effect of its own on it
+    VirulentStrain,
+    // A fifth, purple disease spreads through mutation cards in the infection
+    // deck, and has to be cured as well to win
+    Mutation,
+}
+
+impl Challenge {
+    // The only place a challenge is matched on, the engine goes through the
+    // hooks of its rules
+    pub fn rules(&self) -> &'static dyn ChallengeRules {
+        match self {
+        Challenge::None => &StandardRules,
+        Challenge::VirulentStrain => &VirulentStrainRules,
+        Challenge::Mutation => &MutationRules,
+        }
+    }
+}
+
+// How a challenge changes the rules. Every hook defaults to the standard game.
+pub trait ChallengeRules {
+    // Diseases in play, which all have to be cured to win
+    fn diseases(&self) -> Vec<DiseaseKind> {
+        DiseaseKind::iter().filter(|kind| *kind != DiseaseKind::Purple).collect()
+    }
+
+    // `count` epidemic cards to shuffle into the play deck
+    fn epidemic_cards(&self, _game: &mut Game, count: usize) -> Vec<PlayCard> {
+        vec![PlayCard::Epidemic; count]
+    }
+
+    // Cards shuffled into the infection deck once the first cities are infected
+    fn infection_cards(&self) -> Vec<DiseaseCard> {
+        Vec::new()
+    }
+
+    // React to the epidemic `card`, once its Increase and Infect steps are done
+    fn on_epidemic(&self, _game: &mut Game, _card: &PlayCard) {}
+
+    // React to the infection card of `city`, once its cubes are placed
+    fn on_infection(&self, _game: &mut Game, _city: CityIdx) -> Option<GameOutcome> {
+        None
+    }
+}
+
+struct StandardRules;
+
+impl ChallengeRules for StandardRules {}
+
+struct VirulentStrainRules;
+
+impl ChallengeRules for VirulentStrainRules {
+    fn epidemic_cards(&self, game: &mut Game, count: usize) -> Vec<PlayCard> {
+        let mut effects: Vec<VirulentEffect> = VirulentEffect::iter().collect();
+        effects.shuffle(&mut game.rng);
+        effects.into_iter().take(count).map(PlayCard::VirulentEpidemic).collect()
+    }
+
+    fn on_epidemic(&self, game: &mut Game, card: &PlayCard) {
+        let PlayCard::VirulentEpidemic(effect) = card else {
+            return;
+        };
+
+        if game.virulent.is_none() {
+            // The disease with the most cubes on the board becomes virulent
+            let kind = game.world.diseases.iter().copied().max_by_key(|kind| game.world.cubes_on_board(*kind)).unwrap();
+            game.virulent = Some(VirulentStrain { kind, effects: Vec::new() });
+            game.emit(GameEvent::VirulentStrainChosen(kind));
+        }
+        game.emit(GameEvent::VirulentEpidemic(*effect));
+
+        let strain = game.virulent.as_mut().unwrap();
+        let kind = strain.kind;
+        let cities: Vec<CityIdx> = match effect {
+        effect if effect.is_persistent() => {
+            strain.effects.push(*effect);
+            Vec::new()
+        },
+        VirulentEffect::UnacceptableLoss => {
+            let cubes = &mut game.world.cubes[kind.idx()];
+            *cubes = cubes.saturating_sub(UNACCEPTABLE_LOSS_CUBES);
+            Vec::new()
+        },
+        VirulentEffect::UncountedPopulations => {
+            game.world.cities.iter().filter(|city| city.cubes(kind) == 1).map(|city| city.index).collect()
+        },
+        VirulentEffect::HiddenPocket if game.world.cure_status(kind) == CureStatus::Eradicated => {
+            game.world.cures[kind.idx()] = CureStatus::Cured;
+            game.world.disease_deck.discarded().iter()
+                .filter_map(|card| match card {
+                    DiseaseCard::City(city) if game.world.cities[*city].kind == kind => Some(*city),
+                    _ => None,
+                })
+                .collect()
+        },
+        _ => Vec::new(),
+        };
+
+        for city in cities {
+            if let Some(reason) = game.disease_city(city, kind, 1).loss {
+                game.end_game(reason);
+                return;
+            }
+        }
+    }
+}
+
+struct MutationRules;
+
+impl ChallengeRules for MutationRules {
+    fn diseases(&self) -> Vec<DiseaseKind> {
+        DiseaseKind::iter().collect()
+    }
+
+    fn infection_cards(&self) -> Vec<DiseaseCard> {
+        Mutation::iter().map(DiseaseCard::Mutation).collect()
+    }
+
+    // The purple disease spreads in every city it is already in
+    fn on_infection(&self, game: &mut Game, city: CityIdx) -> Option<GameOutcome> {
+        if game.world.cities[city].cubes(DiseaseKind::Purple) > 0 {
+            return game.disease_city(city, DiseaseKind::Purple, 1).loss;
+        }
+
+        None
+    }
+}
+
+// What a virulent strain epidemic card does after its Infect step
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize, EnumIter)]
+pub enum VirulentEffect {
+    // These last until the virulent strain is cured
+    ChronicEffect,
+    ComplexMolecularStructure,
+    RateEffect,
+
+    UnacceptableLoss,
+    UncountedPopulations,
+    HiddenPocket,
+}
+
+impl VirulentEffect {
+    pub fn title(&self) -> &'static str {
+        match self {
+        VirulentEffect::ChronicEffect => "Chronic Effect",
+        VirulentEffect::ComplexMolecularStructure => "Complex Molecular Structure",
+        VirulentEffect::RateEffect => "Rate Effect",
+        VirulentEffect::UnacceptableLoss => "Unacceptable Loss",
+        VirulentEffect::UncountedPopulations => "Uncounted Populations",
+        VirulentEffect::HiddenPocket => "Hidden Pocket",
+        }
+    }
+
+    pub fn effect(&self) -> &'static str {
+        match self {
+        VirulentEffect::ChronicEffect => "Until it is cured, infecting a city without virulent cubes places 2 of them.",
+        VirulentEffect::ComplexMolecularStructure => "Until it is cured, curing the virulent strain takes 1 more card.",
+        VirulentEffect::RateEffect => "Until it is cured, 1 more infection card is drawn every turn.",
+        VirulentEffect::UnacceptableLoss => "Remove 4 virulent cubes from the supply.",
+        VirulentEffect::UncountedPopulations => "Every city with exactly 1 virulent cube gets another.",
+        VirulentEffect::HiddenPocket => "If the virulent strain was eradicated, it is only cured again and every city of its colour in the infection discard pile gets a cube.",
+        }
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        matches!(self, VirulentEffect::ChronicEffect | VirulentEffect::ComplexMolecularStructure | VirulentEffect::RateEffect)
+    }
+}
+
+// Mutation card drawn from the infection deck instead of a city
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize, EnumIter)]
+pub enum Mutation {
+    Spreads,
+    Intensifies,
+}
+
+impl Mutation {
+    pub fn title(&self) -> &'static str {
+        match self {
+        Mutation::Spreads => "The Mutation Spreads!",
+        Mutation::Intensifies => "The Mutation Intensifies!",
+        }
+    }
+
+    pub fn effect(&self) -> &'static str {
+        match self {
+        Mutation::Spreads => "Draw the bottom infection card and place 3 purple cubes in its city.",
+        Mutation::Intensifies => "Place 1 purple cube in every city which already has purple cubes.",
+        }
+    }
+}
+
+// The disease made virulent by the first epidemic and the lasting effects
+// drawn since
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirulentStrain {
+    pub kind: DiseaseKind,
+    pub effects: Vec<VirulentEffect>,
+}
+
+// Hooks through which the challenge changes the rules
+impl Game {
+    // Epidemic cards shuffled into the play deck
+    pub fn epidemic_cards(&mut self) -> Vec<PlayCard> {
+        let count = self.difficulty.epidemic_cards();
+        self.challenge.rules().epidemic_cards(self, count)
+    }
+
+    // Whether a lasting effect of the virulent strain is in play
+    pub fn virulent_effect(&self, effect: VirulentEffect) -> bool {
+        match &self.virulent {
+        Some(strain) => strain.effects.contains(&effect) && self.world.cure_status(strain.kind) == CureStatus::Active,
+        None => false,
+        }
+    }
+
+    fn is_virulent(&self, kind: DiseaseKind) -> bool {
+        matches!(&self.virulent, Some(strain) if strain.kind == kind)
+    }
+
+    // Infection cards drawn at the end of every turn
+    pub fn infection_rate(&self) -> u8 {
+        self.world.infection_rate() + self.virulent_effect(VirulentEffect::RateEffect) as u8
+    }
+
+    // Cubes placed by the infection card of `city`
+    pub fn infection_cubes(&self, city: CityIdx, kind: DiseaseKind) -> u8 {
+        if self.is_virulent(kind) && self.virulent_effect(VirulentEffect::ChronicEffect) && self.world.cities[city].cubes(kind) == 0 {
+            2
+        } else {
+            1
+        }
+    }
+
+    // Cards of `kind` needed by `player` to discover its cure
+    pub fn cure_cards(&self, player: PlayerId, kind: DiseaseKind) -> usize {
+        let extra = self.is_virulent(kind) && self.virulent_effect(VirulentEffect::ComplexMolecularStructure);
+        self.players[player].profession.cure_cards() + extra as usize
+    }
+
+    // Let the challenge react to the epidemic `card`, once its Increase and
+    // Infect steps are done
+    pub(crate) fn on_epidemic(&mut self, card: &PlayCard) {
+        self.challenge.rules().on_epidemic(self, card);
+    }
+
+    // Shuffle the cards of the challenge into the infection deck, once the
+    // first cities are infected
+    pub(crate) fn extend_infection_deck(&mut self) {
+        let cards = self.challenge.rules().infection_cards();
+        if cards.is_empty() {
+            return;
+        }
+
+        let deck = &mut self.world.disease_deck;
+        for card in cards {
+            deck.cards.push(card);
+            deck.cards_stack.push(deck.cards.len() - 1);
+        }
+        deck.cards_stack.shuffle(&mut self.rng);
+    }
+
+    // Let the challenge react to the infection card of `city`
+    pub(crate) fn on_infection(&mut self, city: CityIdx) -> Option<GameOutcome> {
+        self.challenge.rules().on_infection(self, city)
+    }
+
+    pub(crate) fn resolve_mutation(&mut self, mutation: Mutation) -> Option<GameOutcome> {
+        self.emit(GameEvent::MutationDrawn(mutation));
+
+        let cities: Vec<(CityIdx, u8)> = match mutation {
+        Mutation::Spreads => match self.world.disease_deck.draw_bottom() {
+            Some(DiseaseCard::City(city)) => vec![(city, MUTATION_SPREAD_CUBES)],
+            _ => Vec::new(),
+        },
+        Mutation::Intensifies => {
+            self.world.cities.iter()
+                .filter(|city| city.cubes(DiseaseKind::Purple) > 0)
+                .map(|city| (city.index, 1))
+                .collect()
+        },
+        };
+
+        for (city, cubes) in cities {
+            if let Some(reason) = self.disease_city(city, DiseaseKind::Purple, cubes).loss {
+                return Some(reason);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, GameOutcome, cards::{Action, DiseaseCard, PlayCard}, city::CityIdx, disease::DiseaseKind, log::GameEvent, player::Profession, turn::Turn};
+
+    use super::{Challenge, Mutation, VirulentEffect, PURPLE_CUBES};
+
+    fn mutation_game(purple_cubes: Option<u8>) -> Game {
+        let mut config = shipped_config();
+        config.challenge = Some(Challenge::Mutation);
+        config.purple_cubes = purple_cubes;
+
+        new_game(&config, 6, 2)
+    }
+
+    // Move a city card to the bottom of the infection deck
+    fn city_at_bottom(game: &mut Game) -> CityIdx {
+        let deck = &mut game.world.disease_deck;
+        let pos = deck.cards_stack.iter().position(|card| matches!(deck.cards[*card], DiseaseCard::City(_))).unwrap();
+        let card = deck.cards_stack.remove(pos);
+        deck.cards_stack.insert(0, card);
+
+        match deck.cards[card] {
+        DiseaseCard::City(city) => city,
+        DiseaseCard::Mutation(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn purple_is_only_in_play_with_mutations() {
+        let game = new_game(&shipped_config(), 4, 2);
+        assert!(!game.world.diseases.contains(&DiseaseKind::Purple));
+        assert!(!game.world.disease_deck.cards.iter().any(|card| matches!(card, DiseaseCard::Mutation(_))));
+
+        let mut config = shipped_config();
+        config.challenge = Some(Challenge::Mutation);
+        let game = new_game(&config, 4, 2);
+        assert!(game.world.diseases.contains(&DiseaseKind::Purple));
+        assert_eq!(game.world.disease_deck.cards.len(), config.cities.len() + 2);
+        assert_eq!(game.world.disease_deck.cards_stack.len() + game.world.disease_deck.cards_discard.len(), config.cities.len() + 2);
+    }
+
+    #[test]
+    fn purple_has_its_own_supply() {
+        let mut config = shipped_config();
+        config.challenge = Some(Challenge::Mutation);
+        config.purple_cubes = None;
+        let game = Game::from_config(&config, Some(4));
+        assert_eq!(game.world.cubes_left(DiseaseKind::Purple), PURPLE_CUBES);
+        assert_eq!(game.world.cubes_left(DiseaseKind::Red), config.cubes_per_disease.unwrap());
+
+        assert_eq!(mutation_game(Some(5)).world.cubes_left(DiseaseKind::Purple), 5);
+    }
+
+    #[test]
+    fn mutation_spreads_and_intensifies() {
+        let mut game = mutation_game(None);
+        let city = city_at_bottom(&mut game);
+
+        assert_eq!(game.resolve_mutation(Mutation::Spreads), None);
+        assert_eq!(game.world.cities[city].cubes(DiseaseKind::Purple), 3);
+        assert!(game.log.contains(&GameEvent::MutationDrawn(Mutation::Spreads)));
+
+        // A fourth cube outbreaks instead
+        assert_eq!(game.resolve_mutation(Mutation::Intensifies), None);
+        assert_eq!(game.world.cities[city].cubes(DiseaseKind::Purple), 3);
+        assert!(game.log.contains(&GameEvent::Outbreak { city, kind: DiseaseKind::Purple }));
+    }
+
+    #[test]
+    fn running_out_of_purple_cubes_loses() {
+        let mut game = mutation_game(Some(2));
+        city_at_bottom(&mut game);
+
+        assert_eq!(game.resolve_mutation(Mutation::Spreads), Some(GameOutcome::LostCubes(DiseaseKind::Purple)));
+    }
+
+    #[test]
+    fn purple_is_cured_with_the_chosen_cards_of_any_colour() {
+        let mut game = mutation_game(None);
+        game.current_player = 0;
+        game.turn = Turn::Action(4);
+        game.players[0].profession = Profession::None;
+        // One card of every colour and a few more
+        let mut hand: Vec<CityIdx> = [DiseaseKind::Red, DiseaseKind::Blue, DiseaseKind::Yellow, DiseaseKind::Black].iter()
+            .map(|kind| game.world.cities.iter().find(|city| city.kind == *kind).unwrap().index)
+            .collect();
+        let more: Vec<CityIdx> = (0..game.world.cities.len()).filter(|city| !hand.contains(city)).take(3).collect();
+        hand.extend(more);
+        game.players[0].cards = hand.iter().copied().map(PlayCard::City).collect();
+
+        let cures = game.legal_actions(0).into_iter().filter(|action| matches!(action, Action::DiscoverCure(DiseaseKind::Purple, _))).count();
+        assert_eq!(cures, 21);
+
+        let chosen = vec![hand[0], hand[2], hand[3], hand[5], hand[6]];
+        game.apply_action(0, Action::DiscoverCure(DiseaseKind::Purple, chosen)).unwrap();
+        assert_eq!(game.players[0].cards, vec![PlayCard::City(hand[1]), PlayCard::City(hand[4])]);
+    }
+
+    #[test]
+    fn each_challenge_builds_its_epidemic_cards() {
+        let mut game = Game::from_config(&shipped_config(), Some(2));
+        let count = game.difficulty.epidemic_cards();
+        assert_eq!(game.epidemic_cards(), vec![PlayCard::Epidemic; count]);
+
+        game.challenge = Challenge::VirulentStrain;
+        let cards = game.epidemic_cards();
+        assert_eq!(cards.len(), count);
+        for (i, card) in cards.iter().enumerate() {
+            assert!(matches!(card, PlayCard::VirulentEpidemic(_)));
+            assert!(!cards[..i].contains(card));
+        }
+    }
+
+    #[test]
+    fn virulent_epidemic_picks_the_strain_and_keeps_lasting_effects() {
+        let mut config = shipped_config();
+        config.challenge = Some(Challenge::VirulentStrain);
+        let mut game = new_game(&config, 7, 2);
+        let most = game.world.diseases.iter().copied().max_by_key(|kind| game.world.cubes_on_board(*kind)).unwrap();
+
+        game.on_epidemic(&PlayCard::VirulentEpidemic(VirulentEffect::RateEffect));
+
+        assert_eq!(game.virulent.as_ref().map(|strain| strain.kind), Some(most));
+        assert!(game.virulent_effect(VirulentEffect::RateEffect));
+        assert_eq!(game.infection_rate(), game.world.infection_rate() + 1);
+        // Plain epidemic cards leave the strain alone
+        game.on_epidemic(&PlayCard::Epidemic);
+        assert_eq!(game.virulent.as_ref().unwrap().effects, vec![VirulentEffect::RateEffect]);
+    }
+}
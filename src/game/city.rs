@@ -1,12 +1,23 @@
 use serde::{Serialize, Deserialize};
 
-use super::disease::Disease;
+use super::disease::{DiseaseKind, NUM_DISEASES};
 
 pub type CityIdx = usize;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct City {
     pub name: String,
-    pub disease: Disease,
+    // The colour of the city's infection and player cards
+    pub kind: DiseaseKind,
+    // Cubes of each colour currently in the city
+    pub cubes: [u8; NUM_DISEASES],
     pub index: CityIdx,
+    // Decides who starts the game
+    pub population: u32,
+}
+
+impl City {
+    pub fn cubes(&self, kind: DiseaseKind) -> u8 {
+        self.cubes[kind.idx()]
+    }
 }
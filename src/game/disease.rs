@@ -1,16 +1,47 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
-#[derive(Copy, Clone, Serialize, Deserialize, PartialEq)]
+use super::{city::CityIdx, GameOutcome};
+
+pub const NUM_DISEASES: usize = 5;
+
+#[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, EnumIter)]
 #[derive(Debug)]
 pub enum DiseaseKind {
     Red,
     Blue,
     Yellow,
     Black,
+    // Only in play with the Mutation challenge, no city is purple
+    Purple,
+}
+
+impl DiseaseKind {
+    pub fn idx(&self) -> usize {
+        *self as usize
+    }
+
+    // Whether the city cards of `city` count towards the cure of this disease
+    pub fn cured_by(&self, city: DiseaseKind) -> bool {
+        *self == DiseaseKind::Purple || *self == city
+    }
+}
+
+#[derive(Copy, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum CureStatus {
+    #[default]
+    Active,
+    Cured,
+    // Cured and no cubes of the colour are left on the board
+    Eradicated,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Disease {
-    pub spread: u8,
-    pub kind: DiseaseKind,
-}
\ No newline at end of file
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct OutbreakReport {
+    // Cities that outbroke, in the order they did
+    pub outbreaks: Vec<CityIdx>,
+    // Every cube placed during the chain, in order
+    pub cubes_placed: Vec<(CityIdx, DiseaseKind)>,
+    // Set if placing the cubes lost the game
+    pub loss: Option<GameOutcome>,
+}
@@ -0,0 +1,248 @@
+use super::{Game, actions::{ActionError, ActionOutcome, PendingAction, Request}, cards::{Event, PlayCard, CardIdx, FORECAST_CARDS}, city::CityIdx, player::PlayerId, turn::Turn, log::GameEvent};
+
+impl Game {
+    // Infection cards a Forecast rearranges, top card first
+    pub fn forecast_cards(&self) -> Vec<CardIdx> {
+        self.world.disease_deck.cards_stack.iter().rev().take(FORECAST_CARDS).cloned().collect()
+    }
+
+    // Events can be played at any time, even during another player's turn
+    pub fn check_event(&self, player: PlayerId, event: &Event) -> Result<(), ActionError> {
+        if self.is_over() {
+            return Err(ActionError::GameOver);
+        }
+
+        if player >= self.players.len() {
+            return Err(ActionError::InvalidPlayer(player));
+        }
+
+        if let Some(pending) = &self.pending {
+            return Err(ActionError::AwaitingConsent(pending.target));
+        }
+
+        let p = &self.players[player];
+        let in_hand = p.cards.iter().any(|card| matches!(card, PlayCard::Event(e) if e.same_card(event)));
+        let stored = matches!(&p.stored_event, Some(e) if e.same_card(event));
+        if !in_hand && !stored {
+            return Err(ActionError::NotInHand(PlayCard::Event(event.clone())));
+        }
+
+        let check_city = |city: CityIdx| {
+            if city >= self.world.cities.len() {
+                Err(ActionError::InvalidCity(city))
+            } else {
+                Ok(())
+            }
+        };
+
+        match event {
+        Event::GovermentGrant(city, relocate) => {
+            check_city(*city)?;
+            self.check_station(*city, *relocate)?;
+        },
+        Event::Forecast(order) => {
+            let mut order = order.clone();
+            let mut top = self.forecast_cards();
+            order.sort_unstable();
+            top.sort_unstable();
+            if order != top {
+                return Err(ActionError::InvalidForecast);
+            }
+        },
+        Event::Airlift(pawn, city) => {
+            if *pawn >= self.players.len() {
+                return Err(ActionError::InvalidPlayer(*pawn));
+            }
+            check_city(*city)?;
+            if self.players[*pawn].current_city == *city {
+                return Err(ActionError::AlreadyThere(*city));
+            }
+        },
+        Event::OneQuietNight => {},
+        Event::ResilientPopulation(card) => {
+            if !self.world.disease_deck.cards_discard.contains(card) {
+                return Err(ActionError::NotInInfectionDiscard(*card));
+            }
+        },
+        }
+
+        Ok(())
+    }
+
+    // Play `event` from the hand of `player`, or the one stored by a
+    // Contingency Planner, which is then removed from the game. Airlifting
+    // another player's pawn is put on hold until they agree.
+    pub fn play_event(&mut self, player: PlayerId, event: Event) -> Result<ActionOutcome, ActionError> {
+        self.check_event(player, &event)?;
+
+        if let Event::Airlift(pawn, _) = event {
+            if pawn != player {
+                self.pending = Some(PendingAction { player, target: pawn, request: Request::Event(event) });
+                return Ok(ActionOutcome::AwaitingConsent(pawn));
+            }
+        }
+
+        Ok(self.perform_event(player, event))
+    }
+
+    pub(crate) fn perform_event(&mut self, player: PlayerId, event: Event) -> ActionOutcome {
+        let card = self.players[player].cards.iter()
+            .find(|card| matches!(card, PlayCard::Event(e) if e.same_card(&event)))
+            .cloned();
+        match card {
+        Some(card) => self.discard_card(player, &card),
+        None => self.players[player].stored_event = None,
+        }
+        self.emit(GameEvent::EventPlayed { player, event: event.clone() });
+        // Events may reveal infection cards, so the actions before stay played
+        self.forget_undo();
+
+        let cures = self.world.cures;
+
+        match &event {
+        Event::GovermentGrant(city, relocate) => {
+            self.world.build_research_station(*city, *relocate);
+            self.emit(GameEvent::ResearchStationBuilt { city: *city, relocated_from: *relocate });
+        },
+        Event::Forecast(order) => {
+            let stack = &mut self.world.disease_deck.cards_stack;
+            stack.truncate(stack.len() - order.len());
+            stack.extend(order.iter().rev());
+        },
+        Event::Airlift(pawn, city) => {
+            self.move_pawn(*pawn, *city);
+        },
+        Event::OneQuietNight => self.quiet_night = true,
+        Event::ResilientPopulation(card) => {
+            self.world.disease_deck.cards_discard.retain(|c| c != card);
+        },
+        }
+        self.emit_eradications(cures);
+
+        // Playing an event may bring a player back within the hand limit
+        if let Turn::Discard(p, _) = &self.turn {
+            if *p == player && self.players[player].cards_over_limit() == 0 {
+                if let Some(turn) = self.turn.discard() {
+                    self.turn = turn;
+                }
+            }
+        }
+
+        self.update_event_window();
+
+        ActionOutcome::EventPlayed(event)
+    }
+
+    // Give the players holding an event card a chance to play it before the
+    // next draw, infection card or intensify step. Returns whether a window opened.
+    pub(crate) fn open_event_window(&mut self) -> bool {
+        if !self.turn.is_event_point() {
+            return false;
+        }
+
+        let waiting: Vec<PlayerId> = (0..self.players.len()).filter(|p| self.players[*p].holds_event()).collect();
+        if waiting.is_empty() {
+            return false;
+        }
+
+        self.event_windows += 1;
+        self.turn = Turn::EventWindow(self.event_windows, waiting, Box::new(self.turn.clone()));
+        true
+    }
+
+    // `player` does not want to play an event in the open window
+    pub fn pass_event(&mut self, player: PlayerId) -> Result<(), ActionError> {
+        match &mut self.turn {
+        Turn::EventWindow(_, waiting, _) if waiting.contains(&player) => waiting.retain(|p| *p != player),
+        _ => return Err(ActionError::NoEventWindow(player)),
+        }
+
+        self.update_event_window();
+        Ok(())
+    }
+
+    // Resume the turn, playing out the step the window was opened before
+    pub fn close_event_window(&mut self) {
+        if let Some(turn) = self.turn.close_event_window() {
+            self.turn = turn;
+            self.play_turn();
+        }
+    }
+
+    // Stop waiting for players who have no events left, closing the window
+    // once nobody is left
+    fn update_event_window(&mut self) {
+        let players = &self.players;
+        let done = match &mut self.turn {
+        Turn::EventWindow(_, waiting, _) => {
+            waiting.retain(|p| players[*p].holds_event());
+            waiting.is_empty()
+        },
+        _ => false,
+        };
+
+        if done {
+            self.close_event_window();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, actions::{ActionError, ActionOutcome}, cards::{Event, PlayCard}};
+
+    // A game where the first player holds an Airlift and `city` is away
+    // from every pawn
+    fn airlift_game() -> (Game, usize) {
+        let mut game = new_game(&shipped_config(), 5, 2);
+        game.players[0].cards.push(PlayCard::Event(Event::Airlift(0, 0)));
+        let city = (0..game.world.cities.len()).find(|c| game.players.iter().all(|p| p.current_city != *c)).unwrap();
+
+        (game, city)
+    }
+
+    fn holds_airlift(game: &Game, player: usize) -> bool {
+        game.players[player].cards.iter().any(|card| matches!(card, PlayCard::Event(Event::Airlift(..))))
+    }
+
+    #[test]
+    fn airlift_of_own_pawn_needs_no_consent() {
+        let (mut game, city) = airlift_game();
+
+        let outcome = game.play_event(0, Event::Airlift(0, city));
+
+        assert_eq!(outcome, Ok(ActionOutcome::EventPlayed(Event::Airlift(0, city))));
+        assert_eq!(game.players[0].current_city, city);
+        assert!(!holds_airlift(&game, 0));
+    }
+
+    #[test]
+    fn airlift_of_other_pawn_waits_for_consent() {
+        let (mut game, city) = airlift_game();
+        let from = game.players[1].current_city;
+
+        assert_eq!(game.play_event(0, Event::Airlift(1, city)), Ok(ActionOutcome::AwaitingConsent(1)));
+        assert_eq!(game.players[1].current_city, from);
+        assert!(holds_airlift(&game, 0));
+        assert_eq!(game.play_event(0, Event::Airlift(1, city)), Err(ActionError::AwaitingConsent(1)));
+        assert_eq!(game.answer_consent(0, true), Err(ActionError::NothingToAnswer(0)));
+
+        assert_eq!(game.answer_consent(1, true), Ok(ActionOutcome::EventPlayed(Event::Airlift(1, city))));
+        assert_eq!(game.players[1].current_city, city);
+        assert!(!holds_airlift(&game, 0));
+        assert!(game.pending.is_none());
+    }
+
+    #[test]
+    fn declined_airlift_keeps_the_card() {
+        let (mut game, city) = airlift_game();
+        let from = game.players[1].current_city;
+
+        game.play_event(0, Event::Airlift(1, city)).unwrap();
+
+        assert_eq!(game.answer_consent(1, false), Ok(ActionOutcome::Declined(1)));
+        assert_eq!(game.players[1].current_city, from);
+        assert!(holds_airlift(&game, 0));
+        assert!(game.pending.is_none());
+    }
+}
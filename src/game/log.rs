@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::{Game, GameOutcome, cards::{Action, PlayCard, Event}, challenge::{VirulentEffect, Mutation}, city::CityIdx, disease::{DiseaseKind, CureStatus, NUM_DISEASES}, player::PlayerId};
+
+// Everything that happens in a game, in the order it happened. The GUI, the
+// server and statistics read these instead of the printed messages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    // Reported before the effects of the action
+    ActionTaken { player: PlayerId, action: Action },
+    CardDrawn { player: PlayerId, card: PlayCard },
+    CardsDiscarded { player: PlayerId, cards: Vec<PlayCard> },
+    InfectionCardDrawn(CityIdx),
+    MutationDrawn(Mutation),
+    CityInfected { city: CityIdx, kind: DiseaseKind, cubes: u8 },
+    // The infection was stopped by a cure, an eradication or a role
+    InfectionPrevented { city: CityIdx, kind: DiseaseKind },
+    Outbreak { city: CityIdx, kind: DiseaseKind },
+    EpidemicResolved { city: CityIdx, kind: DiseaseKind },
+    InfectionIntensified,
+    // The infection deck ran out and its discard pile became the deck
+    InfectionDeckReshuffled,
+    InfectionSkipped,
+    VirulentStrainChosen(DiseaseKind),
+    VirulentEpidemic(VirulentEffect),
+    PawnMoved { pawn: PlayerId, from: CityIdx, to: CityIdx },
+    CubesTreated { city: CityIdx, kind: DiseaseKind, removed: u8 },
+    ResearchStationBuilt { city: CityIdx, relocated_from: Option<CityIdx> },
+    CardShared { from: PlayerId, to: PlayerId, card: CityIdx },
+    CureDiscovered(DiseaseKind),
+    DiseaseEradicated(DiseaseKind),
+    EventStored { player: PlayerId, event: Event },
+    EventPlayed { player: PlayerId, event: Event },
+    TurnAdvanced(PlayerId),
+    ActionUndone(PlayerId),
+    ActionRedone(PlayerId),
+    GameOver(GameOutcome),
+}
+
+impl Game {
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        self.log.push(event);
+    }
+
+    // Report the diseases eradicated since the cure statuses were `before`
+    pub(crate) fn emit_eradications(&mut self, before: [CureStatus; NUM_DISEASES]) {
+        for kind in DiseaseKind::iter() {
+            if before[kind.idx()] != CureStatus::Eradicated && self.world.cure_status(kind) == CureStatus::Eradicated {
+                self.emit(GameEvent::DiseaseEradicated(kind));
+            }
+        }
+    }
+
+    pub fn describe_event(&self, event: &GameEvent) -> String {
+        let name = |city: &CityIdx| &self.world.cities[*city].name;
+        let player = |player: &PlayerId| &self.players[*player].name;
+
+        match event {
+        GameEvent::ActionTaken { player: p, action } => format!("{}: {}", player(p), self.describe_action(action)),
+        GameEvent::CardDrawn { player: p, card } => format!("{} drew {}", player(p), self.describe_card(card)),
+        GameEvent::CardsDiscarded { player: p, cards } => {
+            let cards: Vec<String> = cards.iter().map(|card| self.describe_card(card)).collect();
+            format!("{} discarded {}", player(p), cards.join(", "))
+        },
+        GameEvent::InfectionCardDrawn(city) => format!("Infection card {} drawn", name(city)),
+        GameEvent::MutationDrawn(mutation) => format!("{} {}", mutation.title(), mutation.effect()),
+        GameEvent::CityInfected { city, kind, cubes } => format!("{} got {} {:?} cubes", name(city), cubes, kind),
+        GameEvent::InfectionPrevented { city, kind } => format!("{:?} infection of {} was prevented", kind, name(city)),
+        GameEvent::Outbreak { city, kind } => format!("{:?} outbreak in {}!", kind, name(city)),
+        GameEvent::EpidemicResolved { city, kind } => format!("Epidemic of {:?} in {}!", kind, name(city)),
+        GameEvent::InfectionIntensified => "The infection discard pile was shuffled on top of the deck".to_string(),
+        GameEvent::InfectionDeckReshuffled => "The infection deck ran out, its discard pile was shuffled back".to_string(),
+        GameEvent::InfectionSkipped => "One quiet night, no cities are infected".to_string(),
+        GameEvent::VirulentStrainChosen(kind) => format!("{:?} is the virulent strain!", kind),
+        GameEvent::VirulentEpidemic(effect) => format!("Virulent strain epidemic, {}: {}", effect.title(), effect.effect()),
+        GameEvent::PawnMoved { pawn, from, to } => format!("{} moved from {} to {}", player(pawn), name(from), name(to)),
+        GameEvent::CubesTreated { city, kind, removed } => format!("{} {:?} cubes treated in {}", removed, kind, name(city)),
+        GameEvent::ResearchStationBuilt { city, relocated_from: None } => format!("Research station built in {}", name(city)),
+        GameEvent::ResearchStationBuilt { city, relocated_from: Some(from) } => {
+            format!("Research station moved from {} to {}", name(from), name(city))
+        },
+        GameEvent::CardShared { from, to, card } => format!("{} gave {} to {}", player(from), name(card), player(to)),
+        GameEvent::CureDiscovered(kind) => format!("Cure for {:?} discovered!", kind),
+        GameEvent::DiseaseEradicated(kind) => format!("{:?} is eradicated!", kind),
+        GameEvent::EventStored { player: p, event } => format!("{} stored {}", player(p), event.card().title),
+        GameEvent::EventPlayed { player: p, event } => format!("{} played {}", player(p), event.card().title),
+        GameEvent::TurnAdvanced(p) => format!("{}'s turn", player(p)),
+        GameEvent::ActionUndone(p) => format!("{} took back their last action", player(p)),
+        GameEvent::ActionRedone(p) => format!("{} played their action again", player(p)),
+        GameEvent::GameOver(outcome) => outcome.to_string(),
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use std::{path::PathBuf, error::Error, default};
+use std::{path::PathBuf, collections::VecDeque, fmt};
 
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
-use crate::{util::{config::GameConfig, graph::Graph}, game::world::IMACT_RATE};
+use crate::{util::{config::GameConfig, graph::Graph, rng::GameRng}, game::world::{MAX_OUTBREAKS, DEFAULT_CUBES}};
 
-use self::{world::World, player::Player, turn::Turn, city::{City, CityIdx}, disease::Disease, cards::{PlayCard, Deck, NUM_EVENTS, EVENTS, DiseaseCard, Event}};
+use self::{actions::{PendingAction, ActionOutcome}, challenge::{Challenge, VirulentStrain, PURPLE_CUBES}, log::GameEvent, undo::UndoHistory, world::World, player::Player, turn::{Turn, WindowId}, city::{City, CityIdx}, disease::{DiseaseKind, OutbreakReport, CureStatus, NUM_DISEASES}, cards::{PlayCard, NUM_EVENTS, DiseaseCard, Event}};
 
 pub mod turn;
 pub mod city;
@@ -14,8 +14,18 @@ pub mod disease;
 pub mod cards;
 pub mod world;
 pub mod player;
-
-#[derive(Serialize, Deserialize, Default, Debug)]
+pub mod actions;
+pub mod role;
+pub mod challenge;
+pub mod events;
+pub mod setup;
+pub mod log;
+pub mod undo;
+pub mod save;
+pub mod replay;
+pub mod notation;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub enum Difficulty {
     #[default]
     Easy,
@@ -23,14 +33,65 @@ pub enum Difficulty {
     Hard,
 }
 
-#[derive(Default, Debug, Deserialize, Serialize)]
+impl Difficulty {
+    pub fn epidemic_cards(&self) -> usize {
+        match self {
+        Difficulty::Easy => 4,
+        Difficulty::Normal => 5,
+        Difficulty::Hard => 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum GameOutcome {
+    Victory,
+    LostOutbreaks,
+    LostCubes(DiseaseKind),
+    LostPlayerDeckExhausted,
+    // A player left in the middle of the game
+    Abandoned,
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+        GameOutcome::Victory => write!(f, "Victory! All diseases are cured"),
+        GameOutcome::LostOutbreaks => write!(f, "Defeat: {MAX_OUTBREAKS} outbreaks happened"),
+        GameOutcome::LostCubes(kind) => write!(f, "Defeat: ran out of {kind:?} cubes"),
+        GameOutcome::LostPlayerDeckExhausted => write!(f, "Defeat: the player deck ran out of cards"),
+        GameOutcome::Abandoned => write!(f, "The game was abandoned"),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct Game {
     pub world: World,
     pub players: Vec<Player>,
     pub current_player: usize,
     pub turn: Turn,
     pub difficulty: Difficulty,
-    pub end_game: bool,
+    pub challenge: Challenge,
+    // Chosen by the first epidemic of the Virulent Strain challenge
+    pub virulent: Option<VirulentStrain>,
+    // Set once the game is won or lost
+    pub outcome: Option<GameOutcome>,
+    // Action waiting for another player's consent
+    pub pending: Option<PendingAction>,
+    // One Quiet Night was played, the next infection phase is skipped
+    pub quiet_night: bool,
+    // Number of event windows opened so far
+    pub event_windows: WindowId,
+    // Used for every shuffle, so a seed replays the same game
+    pub rng: GameRng,
+    // Everything that happened so far
+    pub log: Vec<GameEvent>,
+    // Actions of the current player which can be taken back
+    pub history: UndoHistory,
+    // Where the game is saved at the start of every turn, if anywhere
+    #[serde(skip)]
+    pub autosave: Option<PathBuf>,
 }
 
 impl Game {
@@ -38,19 +99,19 @@ impl Game {
         Default::default()
     }
 
-    pub fn from_file(yaml_file: PathBuf) -> Result<Self, Box<dyn Error>> {
+    // A `seed` overrides the one in the config, without either a random one is used
+    pub fn from_config(cfg: &GameConfig, seed: Option<u64>) -> Self {
         let mut game = Game::new();
 
-        let cfg: GameConfig = serde_yaml::from_reader(std::fs::File::open(yaml_file)?)?;
+        game.rng = GameRng::new(seed.or(cfg.seed).unwrap_or_else(rand::random));
 
         for (i, city) in cfg.cities.iter().enumerate() {
             game.world.cities.push(City {
                 name: city.name.clone(),
-                disease: Disease {
-                    spread: 0,
-                    kind: city.kind,
-                },
+                kind: city.kind,
+                cubes: [0; NUM_DISEASES],
                 index: i,
+                population: city.population.unwrap_or_default(),
             })
         }
 
@@ -64,17 +125,25 @@ impl Game {
             game.world.map.connect(pair[0], pair[1]);
         }
 
-        if let Some(d) = cfg.difficulty {
-            game.difficulty = d;
+        if let Some(d) = &cfg.difficulty {
+            game.difficulty = d.clone();
         }
+        game.challenge = cfg.challenge.unwrap_or_default();
+        game.world.diseases = game.challenge.rules().diseases();
 
+        game.world.cubes = [cfg.cubes_per_disease.unwrap_or(DEFAULT_CUBES); NUM_DISEASES];
+        game.world.cubes[DiseaseKind::Purple.idx()] = cfg.purple_cubes.unwrap_or(PURPLE_CUBES);
+
+        let start_city = cfg.start_city.unwrap_or_default();
+        if start_city >= num_cities {
+            panic!("WARNING: start city {} does not exist!", start_city);
+        }
+        game.world.start_city = start_city;
+        game.world.build_research_station(start_city, None);
+
+        // Epidemics are added by `build_play_deck` once the hands are dealt
         let num_events = NUM_EVENTS;
-        let num_epidemic_cards = match game.difficulty {
-            Difficulty::Easy => 4,
-            Difficulty::Normal => 5,
-            Difficulty::Hard => 6,
-        };
-        let num_play_cards = num_cities + num_events + num_epidemic_cards;
+        let num_play_cards = num_cities + num_events;
         game.world.play_deck.cards.resize(num_play_cards, PlayCard::Epidemic);
         for i in 0..num_cities {
             game.world.play_deck.cards[i] = PlayCard::City(i);
@@ -85,28 +154,49 @@ impl Game {
         }
 
         game.world.play_deck.cards_stack = (0..num_play_cards).collect();
-        game.world.play_deck.cards_stack.shuffle(&mut thread_rng());
+        game.world.play_deck.cards_stack.shuffle(&mut game.rng);
 
         let mut city_idx: usize = 0;
         game.world.disease_deck.cards.resize_with(num_cities, move || {
             let idx = city_idx;
             city_idx += 1;
-            DiseaseCard(idx)
+            DiseaseCard::City(idx)
         });
         game.world.disease_deck.cards_stack = (0..num_cities).collect();
-        game.world.disease_deck.cards_stack.shuffle(&mut thread_rng());
+        game.world.disease_deck.cards_stack.shuffle(&mut game.rng);
 
-        Ok(game)
+        game
     }
 
+    // Console view of the board, kept for debugging
+    #[allow(dead_code)]
     pub fn display(&self) {
         // TODO: clear screen better way
         print!("{}[2J", 27 as char);
 
         println!("Outbreaks: {}", self.world.outbreaks);
-        println!("Impaction rate: {}({})", self.world.impaction_rate, IMACT_RATE[self.world.impaction_rate as usize]);
+        println!("Impaction rate: {}({})", self.world.impaction_rate, self.world.infection_rate());
+        for kind in self.world.diseases.iter().copied() {
+            println!("{:?} cubes left: {} ({:?})", kind, self.world.cubes_left(kind), self.world.cure_status(kind));
+        }
         for city in self.world.cities.iter() {
-            print!("{} (Disease rate {}): ", city.name, city.disease.spread);
+            print!("{} ({:?}) [", city.name, city.kind);
+            let mut fst_cube = true;
+            for kind in self.world.diseases.iter().copied() {
+                if city.cubes(kind) == 0 {
+                    continue;
+                }
+                if !fst_cube {
+                    print!(", ");
+                }
+                print!("{:?}: {}", kind, city.cubes(kind));
+                fst_cube = false;
+            }
+            print!("]");
+            if self.world.has_research_station(city.index) {
+                print!(" (Research station)");
+            }
+            print!(": ");
 
             let mut fst = true;
             for i in 0..self.world.cities.len() {
@@ -130,84 +220,240 @@ impl Game {
         }
     }
 
-    // return true if pandemic ended the world
-    pub fn resolve_epidemic(&mut self, city: CityIdx) -> bool {
-        false
+    // Outbreak of `kind` in `city` and cascade into its neighbours. Each city
+    // outbreaks at most once per chain.
+    pub fn resolve_epidemic(&mut self, city: CityIdx, kind: DiseaseKind) -> OutbreakReport {
+        let mut report = OutbreakReport::default();
+        let mut pending = VecDeque::from([city]);
+
+        while let Some(curr) = pending.pop_front() {
+            report.outbreaks.push(curr);
+            self.emit(GameEvent::Outbreak { city: curr, kind });
+
+            self.world.outbreaks += 1;
+            if self.world.outbreaks >= MAX_OUTBREAKS {
+                report.loss = Some(GameOutcome::LostOutbreaks);
+                return report;
+            }
+
+            let neighbours: Vec<CityIdx> = self.world.map.neighbours(curr).collect();
+            for neighbour in neighbours {
+                if neighbour == curr || report.outbreaks.contains(&neighbour) || pending.contains(&neighbour) {
+                    continue;
+                }
+
+                if self.infection_blocked(neighbour, kind) {
+                    self.emit(GameEvent::InfectionPrevented { city: neighbour, kind });
+                    continue;
+                }
+
+                if self.world.cities[neighbour].cubes(kind) < 3 {
+                    if !self.world.place_cube(neighbour, kind) {
+                        report.loss = Some(GameOutcome::LostCubes(kind));
+                        return report;
+                    }
+                    report.cubes_placed.push((neighbour, kind));
+                    self.emit(GameEvent::CityInfected { city: neighbour, kind, cubes: 1 });
+                } else {
+                    pending.push_back(neighbour);
+                }
+            }
+        }
+
+        report
     }
 
-    pub fn disease_city(&mut self, city: CityIdx) -> bool{
-        self.world.cities[city].disease.spread += 1;
+    // Place `cubes` cubes of `kind` in `city`. Anything above 3 cubes of
+    // the same colour causes an outbreak instead.
+    pub fn disease_city(&mut self, city: CityIdx, kind: DiseaseKind, cubes: u8) -> OutbreakReport {
+        let mut report = OutbreakReport::default();
 
-        if self.world.cities[city].disease.spread > 3 {
-            self.world.cities[city].disease.spread = 3;
-            return self.resolve_epidemic(city);
+        if self.world.cure_status(kind) == CureStatus::Eradicated || self.infection_blocked(city, kind) {
+            self.emit(GameEvent::InfectionPrevented { city, kind });
+            return report;
+        }
+
+        let mut placed = 0;
+        let mut outbreak = false;
+        for _ in 0..cubes {
+            if self.world.cities[city].cubes(kind) == 3 {
+                outbreak = true;
+                break;
+            }
+
+            if !self.world.place_cube(city, kind) {
+                report.loss = Some(GameOutcome::LostCubes(kind));
+                break;
+            }
+            report.cubes_placed.push((city, kind));
+            placed += 1;
         }
 
-        false
+        if placed > 0 {
+            self.emit(GameEvent::CityInfected { city, kind, cubes: placed });
+        }
+        if outbreak {
+            let chain = self.resolve_epidemic(city, kind);
+            report.outbreaks = chain.outbreaks;
+            report.cubes_placed.extend(chain.cubes_placed);
+            report.loss = chain.loss;
+        }
+
+        report
     }
 
-    // haha, but TODO: handle game end
-    fn end_game(&mut self) {
-        self.end_game = true;
-        panic!("END GAME");
+    // Draw the top infection card. An empty deck is refilled with its
+    // shuffled discard pile instead of ending the game.
+    fn draw_infection_card(&mut self) -> Option<DiseaseCard> {
+        if self.world.disease_deck.cards_stack.is_empty() {
+            self.world.disease_deck.reshuffle_discard(&mut self.rng);
+            self.emit(GameEvent::InfectionDeckReshuffled);
+        }
+
+        self.world.disease_deck.draw()
     }
 
-    pub fn play_turn(&mut self) {
-        let curr_player = &mut self.players[self.current_player];
+    // Run the phases of the turn which need no input from the players
+    pub fn advance(&mut self) -> Option<GameOutcome> {
+        while !self.is_over() && self.turn.is_automatic() {
+            if self.open_event_window() {
+                break;
+            }
+            self.play_phase();
+        }
+
+        self.outcome
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
 
+    pub fn abandon(&mut self) {
+        self.end_game(GameOutcome::Abandoned);
+    }
+
+    fn end_game(&mut self, outcome: GameOutcome) {
+        if self.outcome.is_none() {
+            self.outcome = Some(outcome);
+            self.emit(GameEvent::GameOver(outcome));
+        }
+    }
+
+    // Play out the current phase of the turn, returning the outcome once the game is over
+    pub fn play_turn(&mut self) -> Option<GameOutcome> {
+        self.play_phase();
+        self.outcome
+    }
+
+    fn play_phase(&mut self) {
         match &self.turn {
         Turn::Action(_) => {
-            let action = curr_player.prompt_action();
-
-            if let Some(turn) = self.turn.play_action() {
-                if let Turn::Action(left) = &turn {
-                    println!("Player {} has {} actions left!", curr_player.name, left);
-                } else {
-                    println!("Player {} will now draw 2 cards!", curr_player.name);
-                }
+            let legal_actions = self.legal_actions(self.current_player);
+            let Some(action) = self.players[self.current_player].prompt_action(&legal_actions) else {
+                return;
+            };
 
-                self.turn = turn;
-            } else {
-                panic!("Invalid state after calling Turn::play_action");
+            // A rejected action changes nothing, the player is asked again
+            if let Ok(ActionOutcome::AwaitingConsent(target)) = self.apply_action(self.current_player, action) {
+                let accept = self.players[target].prompt_consent(self.pending.as_ref().unwrap());
+                let _ = self.answer_consent(target, accept);
             }
         },
         Turn::Draw(_) => {
-            let is_epidemic = if let Some(card) = self.world.play_deck.draw() {
-                curr_player.add_card(card.clone());
-                card == PlayCard::Epidemic
+            self.forget_undo();
+
+            let player = self.current_player;
+            let is_epidemic = if let Some((card_idx, card)) = self.world.play_deck.take() {
+                self.emit(GameEvent::CardDrawn { player, card: card.clone() });
+                if card.is_epidemic() {
+                    self.world.play_deck.discard_at(card_idx);
+                    true
+                } else {
+                    self.players[player].add_card(card);
+                    false
+                }
             } else {
-                self.end_game();
+                self.end_game(GameOutcome::LostPlayerDeckExhausted);
                 return;
             };
 
-            if let Some(turn) = self.turn.draw_card(is_epidemic) {
-                if let Turn::Draw(left) = &turn {
-                    println!("Player {} has {} draw left!", curr_player.name, left);
-                } else {
-                    println!("Player {} will spread 2 diseases!", curr_player.name);
-                }
-
+            if let Some(turn) = self.turn.draw_card(is_epidemic, self.infection_rate()) {
                 self.turn = turn;
+                self.check_hand_limit(self.current_player);
             } else {
                 panic!("Invalid state after calling Turn::draw_card");
             }
         },
-        Turn::PandemicInfect => {
+        Turn::PandemicInfect(_) => {
+            // The epidemic card was discarded as soon as it was drawn
+            let epidemic = self.world.play_deck.discarded().pop().unwrap_or_default();
+
+            // Increase
+            self.world.increase_impaction_rate();
+
+            // Infect
+            let loss = match self.world.disease_deck.draw_bottom() {
+            Some(DiseaseCard::City(city)) => {
+                let kind = self.world.cities[city].kind;
+                self.emit(GameEvent::EpidemicResolved { city, kind });
+                self.disease_city(city, kind, 3).loss
+            },
+            // A mutation card at the bottom of the deck is resolved instead
+            Some(DiseaseCard::Mutation(mutation)) => self.resolve_mutation(mutation),
+            None => None,
+            };
+            if let Some(reason) = loss {
+                self.end_game(reason);
+                return;
+            }
+
+            self.on_epidemic(&epidemic);
+            if self.is_over() {
+                return;
+            }
 
+            if let Some(turn) = self.turn.pandemic_infect() {
+                self.turn = turn;
+            } else {
+                panic!("Invalid state after calling Turn::pandemic_infect");
+            }
         },
-        Turn::PandemicIntensify => {
+        Turn::PandemicIntensify(_) => {
+            self.world.disease_deck.reshuffle_discard(&mut self.rng);
+            self.emit(GameEvent::InfectionIntensified);
 
+            if let Some(turn) = self.turn.pandemic_intensify(self.infection_rate()) {
+                self.turn = turn;
+            } else {
+                panic!("Invalid state after calling Turn::pandemic_intensify");
+            }
         },
         Turn::Disease(_) => {
+            if self.quiet_night {
+                self.emit(GameEvent::InfectionSkipped);
+                self.quiet_night = false;
+                self.turn = Turn::NextPlayer;
+                return;
+            }
+
             // Draw disease card from stack
-            if let Some(card) = self.world.disease_deck.draw() {
-                println!("Disease card {} drawn!", self.world.cities[card.0].name);
-                self.disease_city(card.0);
-            } else {
-                self.end_game();
+            let loss = match self.draw_infection_card() {
+            Some(DiseaseCard::City(city)) => {
+                self.emit(GameEvent::InfectionCardDrawn(city));
+                let kind = self.world.cities[city].kind;
+                let report = self.disease_city(city, kind, self.infection_cubes(city, kind));
+                report.loss.or_else(|| self.on_infection(city))
+            },
+            Some(DiseaseCard::Mutation(mutation)) => self.resolve_mutation(mutation),
+            // Every infection card was removed from the game
+            None => None,
             };
+            if let Some(reason) = loss {
+                self.end_game(reason);
+            }
 
-            if self.end_game {
+            if self.is_over() {
                 return;
             }
 
@@ -215,15 +461,304 @@ impl Game {
                 self.turn = turn;
             }
         },
+        Turn::Discard(player, _) => {
+            let player = *player;
+            let count = self.players[player].cards_over_limit();
+            let cards = self.players[player].prompt_discard(count);
+
+            // An invalid discard is asked for again
+            let _ = self.discard_cards(player, cards);
+        },
+        Turn::EventWindow(_, waiting, _) => {
+            for player in waiting.clone() {
+                if let Some(event) = self.players[player].prompt_event() {
+                    let _ = self.play_event(player, event);
+                }
+            }
+
+            self.close_event_window();
+        },
         Turn::NextPlayer => {
             self.current_player = (self.current_player + 1) % self.players.len();
             if let Some(turn) = self.turn.advance_next_player() {
-                println!("Next player {}'s turn!", &self.players[self.current_player].name);
                 self.turn = turn;
+                self.emit(GameEvent::TurnAdvanced(self.current_player));
+
+                if let Some(path) = &self.autosave {
+                    if let Err(e) = self.save(path) {
+                        println!("Autosave failed: {}", e);
+                    }
+                }
             } else {
                 panic!("Invalid state after calling Turn::advance_next_player");
             }
         },
         }
     }
+}
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rand::{Rng, SeedableRng, seq::SliceRandom};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use super::replay::Input;
+    use crate::util::config::{CityDef, GraphDef};
+
+    pub(crate) fn shipped_config() -> GameConfig {
+        GameConfig::from_file(Path::new("assets/config.yaml")).unwrap()
+    }
+
+    // `cities` Red cities connected in a line, 0-1-2-...
+    fn line_config(cities: usize) -> GameConfig {
+        let mut map = Vec::new();
+        for city in 1..cities {
+            map.extend([city - 1, city, city, city - 1]);
+        }
+
+        GameConfig {
+            cities: (0..cities).map(|i| CityDef { name: format!("City {i}"), kind: DiseaseKind::Red, population: None }).collect(),
+            map: GraphDef(map),
+            difficulty: None,
+            cubes_per_disease: None,
+            purple_cubes: None,
+            start_city: None,
+            seed: None,
+            challenge: None,
+        }
+    }
+
+    fn red_cubes(game: &Game) -> Vec<u8> {
+        game.world.cities.iter().map(|city| city.cubes(DiseaseKind::Red)).collect()
+    }
+
+    // A game of `players` players on `config`, set up and waiting for the
+    // first action
+    pub(crate) fn new_game(config: &GameConfig, seed: u64, players: usize) -> Game {
+        let mut game = Game::from_config(config, Some(seed));
+        for id in 0..players {
+            game.players.push(Player { name: format!("Player {id}"), id, ..Default::default() });
+        }
+        game.assign_roles();
+        game.setup();
+        game.advance();
+
+        game
+    }
+
+    // Answer whatever the game waits for with a random legal input
+    pub(crate) fn random_input(game: &Game, rng: &mut ChaCha8Rng) -> Input {
+        if let Some(pending) = &game.pending {
+            return Input::Consent(pending.target, true);
+        }
+
+        match &game.turn {
+        Turn::EventWindow(..) => Input::CloseEventWindow,
+        Turn::Discard(player, _) => {
+            let count = game.players[*player].cards_over_limit();
+            Input::Discard(*player, game.players[*player].cards.iter().take(count).cloned().collect())
+        },
+        _ => {
+            let player = game.current_player;
+            Input::Action(player, game.legal_actions(player).choose(rng).unwrap().clone())
+        },
+        }
+    }
+
+    #[test]
+    fn outbreak_places_a_cube_in_every_neighbour() {
+        let mut game = Game::from_config(&line_config(4), Some(0));
+        game.disease_city(1, DiseaseKind::Red, 3);
+        game.log.clear();
+
+        let report = game.disease_city(1, DiseaseKind::Red, 1);
+
+        assert_eq!(report.outbreaks, vec![1]);
+        assert_eq!(report.cubes_placed, vec![(0, DiseaseKind::Red), (2, DiseaseKind::Red)]);
+        assert_eq!(report.loss, None);
+        assert_eq!(red_cubes(&game), vec![1, 3, 1, 0]);
+        assert_eq!(game.world.outbreaks, 1);
+        assert_eq!(game.log, vec![
+            GameEvent::Outbreak { city: 1, kind: DiseaseKind::Red },
+            GameEvent::CityInfected { city: 0, kind: DiseaseKind::Red, cubes: 1 },
+            GameEvent::CityInfected { city: 2, kind: DiseaseKind::Red, cubes: 1 },
+        ]);
+    }
+
+    #[test]
+    fn outbreak_chain_reaches_each_city_once() {
+        let mut game = Game::from_config(&line_config(5), Some(0));
+        for city in 1..=3 {
+            game.disease_city(city, DiseaseKind::Red, 3);
+        }
+        game.log.clear();
+
+        let report = game.disease_city(2, DiseaseKind::Red, 1);
+
+        // Breadth first: the city drawn, then its neighbours in order
+        assert_eq!(report.outbreaks, vec![2, 1, 3]);
+        assert_eq!(report.cubes_placed, vec![(0, DiseaseKind::Red), (4, DiseaseKind::Red)]);
+        assert_eq!(red_cubes(&game), vec![1, 3, 3, 3, 1]);
+        assert_eq!(game.world.outbreaks, 3);
+        assert_eq!(game.log.iter().filter(|event| matches!(event, GameEvent::Outbreak { .. })).count(), 3);
+    }
+
+    #[test]
+    fn resolve_epidemic_outbreaks_without_placing_in_the_city() {
+        let mut game = Game::from_config(&line_config(3), Some(0));
+
+        let report = game.resolve_epidemic(1, DiseaseKind::Red);
+
+        assert_eq!(report.outbreaks, vec![1]);
+        assert_eq!(report.cubes_placed, vec![(0, DiseaseKind::Red), (2, DiseaseKind::Red)]);
+        assert_eq!(red_cubes(&game), vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn infection_above_three_cubes_outbreaks() {
+        let mut game = Game::from_config(&line_config(3), Some(0));
+        game.disease_city(1, DiseaseKind::Red, 2);
+
+        let report = game.disease_city(1, DiseaseKind::Red, 3);
+
+        assert_eq!(report.cubes_placed[0], (1, DiseaseKind::Red));
+        assert_eq!(report.outbreaks, vec![1]);
+        assert_eq!(red_cubes(&game), vec![1, 3, 1]);
+    }
+
+    #[test]
+    fn empty_supply_loses_the_game() {
+        let mut config = line_config(3);
+        config.cubes_per_disease = Some(2);
+        let mut game = Game::from_config(&config, Some(0));
+
+        let report = game.disease_city(0, DiseaseKind::Red, 3);
+
+        assert_eq!(report.loss, Some(GameOutcome::LostCubes(DiseaseKind::Red)));
+        assert_eq!(game.world.cubes_left(DiseaseKind::Red), 0);
+        assert_eq!(red_cubes(&game), vec![2, 0, 0]);
+    }
+
+    #[test]
+    fn empty_supply_during_an_outbreak_loses_the_game() {
+        let mut config = line_config(3);
+        config.cubes_per_disease = Some(3);
+        let mut game = Game::from_config(&config, Some(0));
+        game.disease_city(1, DiseaseKind::Red, 3);
+
+        let report = game.disease_city(1, DiseaseKind::Red, 1);
+
+        assert_eq!(report.outbreaks, vec![1]);
+        assert_eq!(report.loss, Some(GameOutcome::LostCubes(DiseaseKind::Red)));
+    }
+
+    #[test]
+    fn last_outbreak_loses_the_game() {
+        let mut game = Game::from_config(&line_config(3), Some(0));
+        game.disease_city(1, DiseaseKind::Red, 3);
+        game.world.outbreaks = MAX_OUTBREAKS - 1;
+
+        let report = game.disease_city(1, DiseaseKind::Red, 1);
+
+        assert_eq!(report.loss, Some(GameOutcome::LostOutbreaks));
+        assert_eq!(game.world.outbreaks, MAX_OUTBREAKS);
+        // The chain stops at the losing outbreak
+        assert!(report.cubes_placed.is_empty());
+    }
+
+    #[test]
+    fn eradicated_disease_is_not_placed() {
+        let mut game = Game::from_config(&line_config(3), Some(0));
+        game.world.cures[DiseaseKind::Red.idx()] = CureStatus::Eradicated;
+
+        let report = game.disease_city(0, DiseaseKind::Red, 3);
+
+        assert!(report.cubes_placed.is_empty());
+        assert_eq!(red_cubes(&game), vec![0, 0, 0]);
+        assert_eq!(game.log, vec![GameEvent::InfectionPrevented { city: 0, kind: DiseaseKind::Red }]);
+    }
+
+    #[test]
+    fn epidemic_increases_infects_then_intensifies() {
+        let mut game = new_game(&shipped_config(), 7, 2);
+        // Nobody holds an event card, so no window opens, and no role blocks cubes
+        for player in game.players.iter_mut() {
+            player.cards.clear();
+            player.profession = player::Profession::None;
+        }
+
+        let deck = &mut game.world.play_deck;
+        let epidemic = deck.cards_stack.iter().position(|card| deck.cards[*card] == PlayCard::Epidemic).unwrap();
+        let epidemic = deck.cards_stack.remove(epidemic);
+        deck.cards_stack.push(epidemic);
+
+        let bottom = game.world.disease_deck.cards_stack[0];
+        let DiseaseCard::City(city) = game.world.disease_deck.cards[bottom] else {
+            unreachable!();
+        };
+        let kind = game.world.cities[city].kind;
+        let discarded = game.world.disease_deck.cards_discard.len();
+        let rate = game.world.impaction_rate;
+        game.log.clear();
+
+        game.turn = Turn::Draw(2);
+        game.play_turn();
+        assert!(matches!(game.turn, Turn::PandemicInfect(1)));
+        assert_eq!(game.world.play_deck.cards_discard, vec![epidemic]);
+
+        game.play_turn();
+        assert_eq!(game.world.impaction_rate, rate + 1);
+        assert_eq!(game.world.cities[city].cubes(kind), 3);
+        assert_eq!(game.log, vec![
+            GameEvent::CardDrawn { player: game.current_player, card: PlayCard::Epidemic },
+            GameEvent::EpidemicResolved { city, kind },
+            GameEvent::CityInfected { city, kind, cubes: 3 },
+        ]);
+        assert!(matches!(game.turn, Turn::PandemicIntensify(1)));
+
+        // The discard pile, with the epidemic city, goes back on top
+        game.play_turn();
+        assert_eq!(game.log.last(), Some(&GameEvent::InfectionIntensified));
+        assert!(game.world.disease_deck.cards_discard.is_empty());
+        let stack = &game.world.disease_deck.cards_stack;
+        assert!(stack[stack.len() - discarded - 1..].contains(&bottom));
+        assert!(matches!(game.turn, Turn::Draw(1)));
+    }
+
+    #[test]
+    fn empty_infection_deck_is_reshuffled() {
+        let mut game = new_game(&shipped_config(), 1, 2);
+        let deck = &mut game.world.disease_deck;
+        let mut stack = std::mem::take(&mut deck.cards_stack);
+        deck.cards_discard.append(&mut stack);
+        let cards = deck.cards_discard.len();
+
+        game.turn = Turn::Disease(1);
+        game.play_turn();
+
+        let reshuffled = game.log.iter().position(|event| *event == GameEvent::InfectionDeckReshuffled).unwrap();
+        assert!(matches!(game.log[reshuffled + 1], GameEvent::InfectionCardDrawn(_)));
+        assert_eq!(game.world.disease_deck.cards_stack.len(), cards - 1);
+        assert!(matches!(game.turn, Turn::NextPlayer) || game.is_over());
+    }
+
+    #[test]
+    fn seeded_games_on_shipped_config_end() {
+        let config = shipped_config();
+        for seed in 0..200 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut game = new_game(&config, seed, rng.gen_range(2..=4));
+
+            for _ in 0..5000 {
+                if game.is_over() {
+                    break;
+                }
+                let input = random_input(&game, &mut rng);
+                game.apply_input(input).unwrap();
+            }
+            assert!(game.is_over(), "seed {seed}");
+        }
+    }
 }
\ No newline at end of file
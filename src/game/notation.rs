@@ -0,0 +1,540 @@
+use std::{error::Error, fmt, fs, iter::Peekable, path::Path, slice};
+
+use strum::IntoEnumIterator;
+
+use crate::util::config::GameConfig;
+
+use super::{Game, GameOutcome, cards::{Action, Event, PlayCard, ShareDirection, CardIdx, EVENTS}, city::CityIdx, disease::DiseaseKind, log::GameEvent, player::{Player, PlayerId, Profession}, replay::{Input, Replay}, turn::Turn};
+
+pub const NOTATION_FILE: &str = "last_game.txt";
+
+// Speakers of the lines the engine writes on its own. The parser skips them,
+// but counts the draws and infections to play the events at the same point.
+const SETUP: &str = "Setup";
+const DRAW: &str = "Draw";
+const INFECT: &str = "Infect";
+const EPIDEMIC: &str = "Epidemic";
+const INTENSIFY: &str = "Intensify";
+const ERADICATED: &str = "Eradicated";
+const VIRULENT: &str = "Virulent";
+const GAME_OVER: &str = "Game over";
+
+// Text around the player names in the notation, which the names themselves
+// cannot contain
+const NAME_SEPARATORS: [&str; 9] = [",", "(", ")", ":", "\"", "->", " to ", " from ", "\n"];
+
+#[derive(Debug)]
+pub struct NotationError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Error for NotationError {}
+
+// `Name (Detail)` split into its two parts
+fn split_paren(text: &str) -> Option<(&str, &str)> {
+    text.strip_suffix(')')?.rsplit_once(" (")
+}
+
+fn kind_named(name: &str) -> Result<DiseaseKind, String> {
+    DiseaseKind::iter().find(|kind| format!("{kind:?}") == name).ok_or_else(|| format!("unknown disease {name}"))
+}
+
+fn event_titled(title: &str) -> Result<&'static Event, String> {
+    EVENTS.iter().find(|card| card.title == title).map(|card| &card.kind).ok_or_else(|| format!("unknown event {title}"))
+}
+
+// Why `name` cannot be written down in the notation, if it cannot. The lobby
+// turns such names away.
+pub fn check_player_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.trim() != name {
+        return Err("a name cannot be empty or start or end with spaces".to_string());
+    }
+    if [SETUP, DRAW, INFECT, EPIDEMIC, INTENSIFY, ERADICATED, VIRULENT, GAME_OVER].contains(&name) {
+        return Err(format!("{name} is a reserved name"));
+    }
+    if let Some(separator) = NAME_SEPARATORS.iter().find(|separator| name.contains(*separator)) {
+        return Err(format!("a name cannot contain {:?}", separator.trim()));
+    }
+
+    Ok(())
+}
+
+fn parse_players(players: &str) -> Result<Vec<Player>, String> {
+    players.split(", ").enumerate().map(|(id, player)| {
+        let (name, role) = split_paren(player).ok_or_else(|| format!("expected `Name (Profession)`, got {player}"))?;
+        let profession = Profession::iter()
+            .find(|p| format!("{p:?}") == role)
+            .ok_or_else(|| format!("unknown profession {role}"))?;
+
+        Ok(Player { name: name.to_string(), profession, id, ..Default::default() })
+    }).collect()
+}
+
+impl Game {
+    // The game written one line per action, draw and infection, such as
+    // `T3 Alice: Drive Atlanta->Washington` or `T3 Infect: Madrid +1 (Black)`
+    pub fn to_notation(&self) -> String {
+        let players: Vec<String> = self.players.iter().map(|p| format!("{} ({:?})", p.name, p.profession)).collect();
+        let mut lines = vec![
+            format!("[Seed \"{}\"]", self.rng.seed()),
+            format!("[Players \"{}\"]", players.join(", ")),
+        ];
+        if let Some(outcome) = self.outcome {
+            lines.push(format!("[Result \"{}\"]", outcome));
+        }
+        lines.push(String::new());
+
+        let player = |p: &PlayerId| &self.players[*p].name;
+        let mut turn = 1;
+        let mut setup = true;
+        let mut log = self.log.iter().peekable();
+        while let Some(event) = log.next() {
+            setup &= matches!(event, GameEvent::CityInfected { .. } | GameEvent::InfectionPrevented { .. });
+            if setup {
+                lines.push(format!("{SETUP}: {}", self.infection_notation(event).unwrap()));
+                continue;
+            }
+
+            let text = match event {
+            GameEvent::ActionTaken { player: p, action } => {
+                // The pawn moved by the action is reported right after it
+                let from = match log.peek() {
+                Some(GameEvent::PawnMoved { from, .. }) => Some(*from),
+                _ => None,
+                };
+                format!("{}: {}", player(p), self.action_notation(action, from))
+            },
+            GameEvent::EventPlayed { player: p, event } => format!("{}: Play {}", player(p), self.event_notation(event)),
+            GameEvent::CardsDiscarded { player: p, cards } => {
+                let cards: Vec<String> = cards.iter().map(|card| self.card_notation(card)).collect();
+                format!("{}: Discard {}", player(p), cards.join(", "))
+            },
+            GameEvent::ActionUndone(p) => format!("{}: Undo", player(p)),
+            GameEvent::ActionRedone(p) => format!("{}: Redo", player(p)),
+            GameEvent::CardDrawn { player: p, card } => format!("{DRAW}: {} drew {}", player(p), self.card_notation(card)),
+            GameEvent::InfectionCardDrawn(_) => format!("{INFECT}: {}", self.infection_chain(&mut log)),
+            GameEvent::MutationDrawn(mutation) => {
+                let chain = self.infection_chain(&mut log);
+                if chain.is_empty() {
+                    format!("{INFECT}: {}", mutation.title())
+                } else {
+                    format!("{INFECT}: {}, {}", mutation.title(), chain)
+                }
+            },
+            GameEvent::InfectionSkipped => format!("{INFECT}: skipped by One Quiet Night"),
+            GameEvent::EpidemicResolved { .. } => format!("{EPIDEMIC}: {}", self.infection_chain(&mut log)),
+            GameEvent::VirulentStrainChosen(kind) => format!("{VIRULENT}: {kind:?}"),
+            GameEvent::VirulentEpidemic(effect) => {
+                let chain = self.infection_chain(&mut log);
+                if chain.is_empty() {
+                    format!("{VIRULENT}: {}", effect.title())
+                } else {
+                    format!("{VIRULENT}: {}, {}", effect.title(), chain)
+                }
+            },
+            GameEvent::InfectionIntensified => format!("{INTENSIFY}: infection discard pile put back on top"),
+            GameEvent::DiseaseEradicated(kind) => format!("{ERADICATED}: {kind:?}"),
+            GameEvent::GameOver(outcome) => format!("{GAME_OVER}: {outcome}"),
+            GameEvent::TurnAdvanced(_) => {
+                turn += 1;
+                continue;
+            },
+            // Results of the actions and events written above
+            _ => continue,
+            };
+            lines.push(format!("T{turn} {text}"));
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn save_notation(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_notation())?;
+        Ok(())
+    }
+
+    // The cubes placed and the outbreaks following an infection card
+    fn infection_chain(&self, log: &mut Peekable<slice::Iter<GameEvent>>) -> String {
+        let mut parts = Vec::new();
+        while let Some(part) = log.peek().and_then(|event| self.infection_notation(event)) {
+            parts.push(part);
+            log.next();
+        }
+
+        parts.join(", ")
+    }
+
+    fn infection_notation(&self, event: &GameEvent) -> Option<String> {
+        let name = |city: &CityIdx| &self.world.cities[*city].name;
+
+        match event {
+        GameEvent::CityInfected { city, kind, cubes } => Some(format!("{} +{} ({:?})", name(city), cubes, kind)),
+        GameEvent::InfectionPrevented { city, kind } => Some(format!("{} prevented ({:?})", name(city), kind)),
+        GameEvent::Outbreak { city, .. } => Some(format!("outbreak {}", name(city))),
+        _ => None,
+        }
+    }
+
+    fn action_notation(&self, action: &Action, from: Option<CityIdx>) -> String {
+        let name = |city: &CityIdx| &self.world.cities[*city].name;
+        let player = |p: &PlayerId| &self.players[*p].name;
+        // Pawns moved by the Dispatcher come first, in parentheses
+        let route = |city: &CityIdx, pawn: &Option<PlayerId>| {
+            let pawn = pawn.map(|p| format!("({}) ", player(&p))).unwrap_or_default();
+            match from {
+            Some(from) => format!("{}{}->{}", pawn, name(&from), name(city)),
+            None => format!("{}{}", pawn, name(city)),
+            }
+        };
+
+        match action {
+        Action::Drive(city, pawn) => format!("Drive {}", route(city, pawn)),
+        Action::Direct(city, pawn) => format!("Direct {}", route(city, pawn)),
+        Action::Charter(city, pawn) => format!("Charter {}", route(city, pawn)),
+        Action::Shuttle(city, pawn) => format!("Shuttle {}", route(city, pawn)),
+        Action::MovePawnToPawn { pawn, to } => format!("Dispatch {}->{}", player(pawn), player(to)),
+        Action::TreatDisease(city, kind) => format!("Treat {} ({:?})", name(city), kind),
+        Action::BuildResearchCenter(city, relocate) => format!("Build {}", self.station_notation(*city, *relocate)),
+        Action::DiscoverCure(kind, cards) => {
+            let cards: Vec<&str> = cards.iter().map(|city| name(city).as_str()).collect();
+            format!("Cure {:?}: {}", kind, cards.join(", "))
+        },
+        Action::RetrieveEvent(event) => format!("Retrieve {}", event.card().title),
+        Action::ShareKnowledge { with, card, direction: ShareDirection::Give } => {
+            format!("Give {} to {}", name(card), player(with))
+        },
+        Action::ShareKnowledge { with, card, direction: ShareDirection::Take } => {
+            format!("Take {} from {}", name(card), player(with))
+        },
+        }
+    }
+
+    fn event_notation(&self, event: &Event) -> String {
+        let title = event.card().title;
+
+        match event {
+        Event::GovermentGrant(city, relocate) => format!("{}: {}", title, self.station_notation(*city, *relocate)),
+        Event::Forecast(order) => {
+            let cards: Vec<String> = order.iter().map(|card| self.describe_infection_card(*card)).collect();
+            format!("{}: {}", title, cards.join(", "))
+        },
+        Event::Airlift(pawn, city) => format!("{}: {}->{}", title, self.players[*pawn].name, self.world.cities[*city].name),
+        Event::OneQuietNight => title.to_string(),
+        Event::ResilientPopulation(card) => format!("{}: {}", title, self.describe_infection_card(*card)),
+        }
+    }
+
+    // `city`, followed by the research station moved there if any
+    fn station_notation(&self, city: CityIdx, relocate: Option<CityIdx>) -> String {
+        let name = |city: CityIdx| &self.world.cities[city].name;
+
+        match relocate {
+        Some(from) => format!("{} (from {})", name(city), name(from)),
+        None => name(city).clone(),
+        }
+    }
+
+    fn card_notation(&self, card: &PlayCard) -> String {
+        match card {
+        PlayCard::City(city) => self.world.cities[*city].name.clone(),
+        PlayCard::Event(event) => event.card().title.to_string(),
+        PlayCard::Epidemic => "Epidemic".to_string(),
+        PlayCard::VirulentEpidemic(effect) => format!("Epidemic ({})", effect.title()),
+        }
+    }
+
+    fn city_named(&self, name: &str) -> Result<CityIdx, String> {
+        self.world.cities.iter().position(|city| city.name == name).ok_or_else(|| format!("unknown city {name}"))
+    }
+
+    fn player_named(&self, name: &str) -> Result<PlayerId, String> {
+        self.players.iter().position(|p| p.name == name).ok_or_else(|| format!("unknown player {name}"))
+    }
+
+    fn infection_card_named(&self, name: &str) -> Result<CardIdx, String> {
+        (0..self.world.disease_deck.cards.len())
+            .find(|card| self.describe_infection_card(*card) == name)
+            .ok_or_else(|| format!("no infection card for {name}"))
+    }
+
+    fn card_named(&self, name: &str) -> Result<PlayCard, String> {
+        if name == "Epidemic" {
+            return Ok(PlayCard::Epidemic);
+        }
+
+        match event_titled(name) {
+        Ok(event) => Ok(PlayCard::Event(event.clone())),
+        Err(_) => self.city_named(name).map(PlayCard::City),
+        }
+    }
+
+    fn parse_station(&self, text: &str) -> Result<(CityIdx, Option<CityIdx>), String> {
+        match split_paren(text).and_then(|(city, from)| Some((city, from.strip_prefix("from ")?))) {
+        Some((city, from)) => Ok((self.city_named(city)?, Some(self.city_named(from)?))),
+        None => Ok((self.city_named(text)?, None)),
+        }
+    }
+
+    fn parse_event(&self, text: &str) -> Result<Event, String> {
+        let (title, params) = text.split_once(": ").unwrap_or((text, ""));
+
+        Ok(match event_titled(title)? {
+        Event::GovermentGrant(..) => {
+            let (city, relocate) = self.parse_station(params)?;
+            Event::GovermentGrant(city, relocate)
+        },
+        Event::Forecast(_) => {
+            let order = params.split(", ").map(|card| self.infection_card_named(card)).collect::<Result<_, _>>()?;
+            Event::Forecast(order)
+        },
+        Event::Airlift(..) => {
+            let (pawn, city) = params.split_once("->").ok_or_else(|| format!("expected `Player->City`, got {params}"))?;
+            Event::Airlift(self.player_named(pawn)?, self.city_named(city)?)
+        },
+        Event::OneQuietNight => Event::OneQuietNight,
+        Event::ResilientPopulation(_) => Event::ResilientPopulation(self.infection_card_named(params)?),
+        })
+    }
+
+    // What `player` decided in a line of notation
+    fn parse_input(&self, player: PlayerId, text: &str) -> Result<Input, String> {
+        let (verb, rest) = text.split_once(' ').unwrap_or((text, ""));
+
+        let action = match verb {
+        "Undo" => return Ok(Input::Undo(player)),
+        "Redo" => return Ok(Input::Redo(player)),
+        "Play" => return Ok(Input::Event(player, self.parse_event(rest)?)),
+        "Discard" => {
+            let cards = rest.split(", ").map(|card| self.card_named(card)).collect::<Result<_, _>>()?;
+            return Ok(Input::Discard(player, cards));
+        },
+        "Drive" | "Direct" | "Charter" | "Shuttle" => {
+            let (pawn, route) = match rest.strip_prefix('(').and_then(|rest| rest.split_once(") ")) {
+            Some((pawn, route)) => (Some(self.player_named(pawn)?), route),
+            None => (None, rest),
+            };
+            let city = self.city_named(route.rsplit_once("->").map_or(route, |(_, to)| to))?;
+
+            match verb {
+            "Drive" => Action::Drive(city, pawn),
+            "Direct" => Action::Direct(city, pawn),
+            "Charter" => Action::Charter(city, pawn),
+            _ => Action::Shuttle(city, pawn),
+            }
+        },
+        "Dispatch" => {
+            let (pawn, to) = rest.split_once("->").ok_or_else(|| format!("expected `Player->Player`, got {rest}"))?;
+            Action::MovePawnToPawn { pawn: self.player_named(pawn)?, to: self.player_named(to)? }
+        },
+        "Treat" => {
+            let (city, kind) = split_paren(rest).ok_or_else(|| format!("expected `City (Disease)`, got {rest}"))?;
+            Action::TreatDisease(self.city_named(city)?, kind_named(kind)?)
+        },
+        "Build" => {
+            let (city, relocate) = self.parse_station(rest)?;
+            Action::BuildResearchCenter(city, relocate)
+        },
+        "Cure" => {
+            let (kind, cards) = rest.split_once(": ").ok_or_else(|| format!("expected `Disease: City, City, ...`, got {rest}"))?;
+            let cards = cards.split(", ").map(|city| self.city_named(city)).collect::<Result<_, _>>()?;
+            Action::DiscoverCure(kind_named(kind)?, cards)
+        },
+        "Retrieve" => Action::RetrieveEvent(event_titled(rest)?.clone()),
+        "Give" | "Take" => {
+            let (card, with, direction) = match verb {
+            "Give" => rest.rsplit_once(" to ").map(|(card, with)| (card, with, ShareDirection::Give)),
+            _ => rest.rsplit_once(" from ").map(|(card, with)| (card, with, ShareDirection::Take)),
+            }.ok_or_else(|| format!("expected `{verb} City to/from Player`, got {rest}"))?;
+            Action::ShareKnowledge { with: self.player_named(with)?, card: self.city_named(card)?, direction }
+        },
+        _ => return Err(format!("unknown action {verb}")),
+        };
+
+        Ok(Input::Action(player, action))
+    }
+
+    // Draws, infection cards and intensify steps made so far, the points
+    // before which event windows open
+    fn event_points(&self) -> usize {
+        self.log.iter().filter(|event| matches!(event,
+            GameEvent::CardDrawn { .. } | GameEvent::InfectionCardDrawn(_) | GameEvent::MutationDrawn(_) | GameEvent::InfectionSkipped | GameEvent::InfectionIntensified
+            // The draw which found the player deck empty
+            | GameEvent::GameOver(GameOutcome::LostPlayerDeckExhausted)
+        )).count()
+    }
+}
+
+impl Replay {
+    // Rebuild a game from its notation, naming the cities after `config`.
+    // Only the players' lines are played again, the engine's lines tell in
+    // which event window the events were played.
+    pub fn from_notation(text: &str, config: GameConfig) -> Result<Self, NotationError> {
+        let mut seed = None;
+        let mut players = None;
+        for (line, tag) in text.lines().enumerate() {
+            let error = |reason: String| NotationError { line: line + 1, reason };
+            let Some(tag) = tag.trim().strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) else {
+                continue;
+            };
+
+            let (name, value) = tag.split_once(' ').ok_or_else(|| error(format!("malformed tag {tag}")))?;
+            let value = value.trim_matches('"');
+            match name {
+            "Seed" => seed = Some(value.parse::<u64>().map_err(|e| error(e.to_string()))?),
+            "Players" => players = Some(parse_players(value).map_err(error)?),
+            _ => {},
+            }
+        }
+
+        let missing = |tag: &str| NotationError { line: 1, reason: format!("missing {tag} tag") };
+        let mut game = Game::from_config(&config, Some(seed.ok_or_else(|| missing("Seed"))?));
+        game.players = players.ok_or_else(|| missing("Players"))?;
+        let mut replay = Replay::new(config, &game);
+        let mut game = replay.start();
+
+        let mut points = 0;
+        for (line, text) in text.lines().enumerate() {
+            let error = |reason: String| NotationError { line: line + 1, reason };
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('[') || text.starts_with(';') || text.starts_with(SETUP) {
+                continue;
+            }
+
+            let (speaker, text) = text.split_once(' ')
+                .filter(|(turn, _)| turn.starts_with('T'))
+                .and_then(|(_, text)| text.split_once(':'))
+                .ok_or_else(|| error(format!("expected `T<turn> <player>: <action>`, got {text}")))?;
+
+            match speaker {
+            DRAW | INFECT | INTENSIFY => points += 1,
+            GAME_OVER if text.trim() == GameOutcome::LostPlayerDeckExhausted.to_string() => points += 1,
+            EPIDEMIC | ERADICATED | VIRULENT | GAME_OVER => {},
+            _ => {
+                let player = game.player_named(speaker).map_err(error)?;
+                let input = game.parse_input(player, text.trim()).map_err(error)?;
+                replay.play_back(&mut game, input, points).map_err(error)?;
+            },
+            }
+        }
+
+        while matches!(game.turn, Turn::EventWindow(..)) && game.event_points() < points {
+            replay.play_back_input(&mut game, Input::CloseEventWindow).map_err(|reason| NotationError { line: text.lines().count(), reason })?;
+        }
+
+        Ok(replay)
+    }
+
+    pub fn load_notation(path: &Path, config: GameConfig) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Self::from_notation(&text, config)?)
+    }
+
+    // Apply `input` once `points` draws and infections were made, closing the
+    // event windows nobody played in on the way
+    fn play_back(&mut self, game: &mut Game, input: Input, points: usize) -> Result<(), String> {
+        let event = matches!(input, Input::Event(..));
+        while matches!(game.turn, Turn::EventWindow(..)) && (!event || game.event_points() < points) {
+            self.play_back_input(game, Input::CloseEventWindow)?;
+        }
+
+        self.play_back_input(game, input)?;
+
+        // Only the actions which were agreed to are written down
+        if let Some(pending) = &game.pending {
+            let target = pending.target;
+            self.play_back_input(game, Input::Consent(target, true))?;
+        }
+
+        Ok(())
+    }
+
+    fn play_back_input(&mut self, game: &mut Game, input: Input) -> Result<(), String> {
+        game.apply_input(input.clone()).map_err(|e| e.to_string())?;
+        self.record(input);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::game::{tests::{new_game, random_input, shipped_config}, challenge::Challenge, replay::{Replay, ReplayStates}};
+
+    use super::check_player_name;
+
+    #[test]
+    fn notation_rebuilds_the_game() {
+        for challenge in [None, Some(Challenge::VirulentStrain), Some(Challenge::Mutation)] {
+            let mut config = shipped_config();
+            config.challenge = challenge;
+
+            for seed in 0..20 {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let mut game = new_game(&config, seed, rng.gen_range(2..=4));
+                while !game.is_over() {
+                    let input = random_input(&game, &mut rng);
+                    game.apply_input(input).unwrap();
+                }
+
+                let notation = game.to_notation();
+                let replay = Replay::from_notation(&notation, config.clone()).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+                let mut states = ReplayStates::new(replay);
+                assert!(states.diverged.is_none(), "{challenge:?}, seed {seed}");
+                let last = states.last();
+                assert_eq!(states.state(last).to_notation(), notation, "{challenge:?}, seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        let game = new_game(&shipped_config(), 1, 2);
+        let notation = game.to_notation();
+
+        let error = Replay::from_notation(&format!("{notation}T1 Nobody: Drive Atlanta->Chicago\n"), shipped_config()).unwrap_err();
+        assert_eq!(error.line, notation.lines().count() + 1);
+
+        let error = Replay::from_notation("[Players \"Alice (Medic)\"]\n", shipped_config()).unwrap_err();
+        assert_eq!(error.reason, "missing Seed tag");
+    }
+
+    #[test]
+    fn names_breaking_the_notation_are_refused() {
+        for name in ["", " Alice", "Draw", "Infect", "Intensify", "Setup", "Ann, Bob", "Bob (Medic)", "Dr: Who", "A->B", "Go to bed", "Jo from Rome", "\"Q\""] {
+            assert!(check_player_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn unusual_names_rebuild_the_game() {
+        let names = ["Ann Marie", "T2", "Draw Bot", "Dr. O'Neil-Smith"];
+        for name in names {
+            assert_eq!(check_player_name(name), Ok(()));
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut game = new_game(&shipped_config(), 5, names.len());
+        for (player, name) in game.players.iter_mut().zip(names) {
+            player.name = name.to_string();
+        }
+        while !game.is_over() {
+            let input = random_input(&game, &mut rng);
+            game.apply_input(input).unwrap();
+        }
+
+        let notation = game.to_notation();
+        let mut states = ReplayStates::new(Replay::from_notation(&notation, shipped_config()).unwrap());
+        let last = states.last();
+        assert_eq!(states.state(last).to_notation(), notation);
+    }
+}
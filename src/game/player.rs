@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
-use super::{city::CityIdx, cards::{PlayCard, Action}};
+use super::{actions::PendingAction, city::CityIdx, cards::{PlayCard, Action, Event}, disease::DiseaseKind, world::World};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum Profession {
     #[default]
     None,
@@ -18,22 +19,65 @@ pub enum Profession {
 
 pub type PlayerId = usize;
 
+pub const HAND_LIMIT: usize = 7;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
     pub cards: Vec<PlayCard>,
     pub profession: Profession,
     pub current_city: CityIdx,
-    pub additional_actions: Vec<Action>,
+    // Event card kept aside by the Contingency Planner
+    pub stored_event: Option<Event>,
     pub id: PlayerId,
 }
 
 impl Player {
     pub fn add_card(&mut self, card: PlayCard) {
-        todo!();
+        self.cards.push(card);
+    }
+
+    pub fn cards_over_limit(&self) -> usize {
+        self.cards.len().saturating_sub(HAND_LIMIT)
+    }
+
+    pub fn holds_event(&self) -> bool {
+        self.stored_event.is_some() || self.cards.iter().any(|card| matches!(card, PlayCard::Event(_)))
+    }
+
+    pub fn city_cards(&self, world: &World, kind: DiseaseKind) -> Vec<CityIdx> {
+        self.cards.iter().filter_map(|card| match card {
+            PlayCard::City(city) if kind.cured_by(world.cities[*city].kind) => Some(*city),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn remove_card(&mut self, card: &PlayCard) -> bool {
+        if let Some(idx) = self.cards.iter().position(|c| c == card) {
+            self.cards.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
+    // TODO: ask the actual player instead of picking the first legal action
+    pub fn prompt_action(&self, legal_actions: &[Action]) -> Option<Action> {
+        legal_actions.first().cloned()
+    }
+
+    // TODO: ask the actual player
+    pub fn prompt_consent(&self, _pending: &PendingAction) -> bool {
+        true
+    }
+
+    // TODO: ask the actual player instead of dropping the newest cards
+    pub fn prompt_discard(&self, count: usize) -> Vec<PlayCard> {
+        self.cards.iter().rev().take(count).cloned().collect()
     }
 
-    pub fn prompt_action(&mut self) -> Action {
-        Action::Direct(0)
+    // TODO: ask the actual player, for now events are only played over the network
+    pub fn prompt_event(&self) -> Option<Event> {
+        None
     }
 }
@@ -0,0 +1,256 @@
+use std::{error::Error, fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::config::GameConfig;
+
+use super::{Game, actions::ActionError, cards::{Action, PlayCard, Event}, log::GameEvent, player::{Player, PlayerId}, save::{read_file, write_file}};
+
+pub const REPLAY_VERSION: u32 = 5;
+pub const REPLAY_FILE: &str = "last_game.replay";
+
+// Inputs between two games kept by `ReplayStates`
+const CHECKPOINT_INTERVAL: usize = 32;
+
+// A decision taken by a player, or on their behalf by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    Action(PlayerId, Action),
+    Consent(PlayerId, bool),
+    Discard(PlayerId, Vec<PlayCard>),
+    Event(PlayerId, Event),
+    PassEvent(PlayerId),
+    // Nobody played an event before the window timed out
+    CloseEventWindow,
+    Undo(PlayerId),
+    Redo(PlayerId),
+}
+
+// Everything needed to play a game again: the same config, seed and
+// players give the same setup, after which the inputs are applied in order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    pub config: GameConfig,
+    pub seed: u64,
+    // Players as they left the lobby, before the free roles were handed out
+    pub players: Vec<Player>,
+    pub inputs: Vec<Input>,
+}
+
+impl Game {
+    // Apply `input` and play on until the game needs the next one
+    pub fn apply_input(&mut self, input: Input) -> Result<(), ActionError> {
+        match input {
+        Input::Action(player, action) => { self.apply_action(player, action)?; },
+        Input::Consent(player, accept) => {
+            // The action may have become invalid while waiting, the answer still clears it
+            if let Err(ActionError::NothingToAnswer(p)) = self.answer_consent(player, accept) {
+                return Err(ActionError::NothingToAnswer(p));
+            }
+        },
+        Input::Discard(player, cards) => self.discard_cards(player, cards)?,
+        Input::Event(player, event) => { self.play_event(player, event)?; },
+        Input::PassEvent(player) => self.pass_event(player)?,
+        Input::CloseEventWindow => self.close_event_window(),
+        Input::Undo(player) => self.undo(player)?,
+        Input::Redo(player) => self.redo(player)?,
+        }
+
+        self.advance();
+        Ok(())
+    }
+
+    // Turns started so far, counting from 1
+    pub fn turn_number(&self) -> usize {
+        1 + self.log.iter().filter(|event| matches!(event, GameEvent::TurnAdvanced(_))).count()
+    }
+}
+
+impl Replay {
+    // Start recording `game`, which was created from `config` and not set up yet
+    pub fn new(config: GameConfig, game: &Game) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            config,
+            seed: game.rng.seed(),
+            players: game.players.clone(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    // The game right after setup, before any input
+    pub fn start(&self) -> Game {
+        let mut game = Game::from_config(&self.config, Some(self.seed));
+        game.players = self.players.clone();
+        game.assign_roles();
+        game.setup();
+
+        game
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        write_file(path, self)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let replay: Replay = read_file(path)?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(format!("replay version {} is not supported, expected {}", replay.version, REPLAY_VERSION).into());
+        }
+
+        Ok(replay)
+    }
+}
+
+// An input of a replay the engine did not accept
+#[derive(Debug)]
+pub struct ReplayDiverged {
+    // Index of the input in the replay
+    pub input: usize,
+    pub error: ActionError,
+}
+
+impl fmt::Display for ReplayDiverged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the replay diverged at input {}: {}", self.input + 1, self.error)
+    }
+}
+
+impl Error for ReplayDiverged {}
+
+// The game after setup and after each input of a replay. Only every
+// CHECKPOINT_INTERVAL-th state is kept, the others are played again from
+// the checkpoint before them when asked for.
+pub struct ReplayStates {
+    replay: Replay,
+    checkpoints: Vec<Game>,
+    // Turn number and current player of every state
+    turns: Vec<(usize, PlayerId)>,
+    // Last state asked for, so that stepping forward applies a single input
+    current: (usize, Game),
+    // Set when the inputs stop before the end of the replay
+    pub diverged: Option<ReplayDiverged>,
+}
+
+impl ReplayStates {
+    pub fn new(replay: Replay) -> Self {
+        let mut game = replay.start();
+        let mut checkpoints = vec![game.clone()];
+        let mut turns = vec![(game.turn_number(), game.current_player)];
+        let mut diverged = None;
+
+        for (i, input) in replay.inputs.iter().enumerate() {
+            if let Err(error) = game.apply_input(input.clone()) {
+                diverged = Some(ReplayDiverged { input: i, error });
+                break;
+            }
+
+            turns.push((game.turn_number(), game.current_player));
+            if (i + 1) % CHECKPOINT_INTERVAL == 0 {
+                checkpoints.push(game.clone());
+            }
+        }
+
+        let current = (0, checkpoints[0].clone());
+        Self { replay, checkpoints, turns, current, diverged }
+    }
+
+    // Index of the last state, the game after the last accepted input
+    pub fn last(&self) -> usize {
+        self.turns.len() - 1
+    }
+
+    pub fn turn(&self, state: usize) -> (usize, PlayerId) {
+        self.turns[state]
+    }
+
+    pub fn state(&mut self, state: usize) -> &Game {
+        let state = state.min(self.last());
+        let checkpoint = state / CHECKPOINT_INTERVAL;
+
+        if self.current.0 > state || self.current.0 < checkpoint * CHECKPOINT_INTERVAL {
+            self.current = (checkpoint * CHECKPOINT_INTERVAL, self.checkpoints[checkpoint].clone());
+        }
+
+        let (at, game) = &mut self.current;
+        for input in self.replay.inputs[*at..state].iter() {
+            game.apply_input(input.clone()).expect("the input was accepted when the checkpoints were taken");
+        }
+        *at = state;
+
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, seq::SliceRandom};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::game::{Game, tests::{random_input, shipped_config}, actions::ActionError, player::Player};
+
+    use super::{Input, Replay, ReplayStates, CHECKPOINT_INTERVAL};
+
+    // A replay of a whole seeded game, with the notation of the game after
+    // setup and after each input
+    fn recorded_game(seed: u64) -> (Replay, Vec<String>) {
+        let config = shipped_config();
+        let mut game = Game::from_config(&config, Some(seed));
+        for id in 0..3 {
+            game.players.push(Player { name: format!("Player {id}"), id, ..Default::default() });
+        }
+
+        let mut replay = Replay::new(config, &game);
+        game.assign_roles();
+        game.setup();
+        game.advance();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut notations = vec![game.to_notation()];
+        while !game.is_over() {
+            let input = random_input(&game, &mut rng);
+            game.apply_input(input.clone()).unwrap();
+            replay.record(input);
+            notations.push(game.to_notation());
+        }
+
+        (replay, notations)
+    }
+
+    #[test]
+    fn states_are_rebuilt_in_any_order() {
+        let (replay, notations) = recorded_game(3);
+        assert!(replay.inputs.len() > 2 * CHECKPOINT_INTERVAL);
+        let mut states = ReplayStates::new(replay);
+        assert!(states.diverged.is_none());
+        assert_eq!(states.last(), notations.len() - 1);
+
+        let mut order: Vec<usize> = (0..notations.len()).collect();
+        order.shuffle(&mut ChaCha8Rng::seed_from_u64(0));
+        for state in order.into_iter().chain((0..notations.len()).rev()) {
+            assert_eq!(states.state(state).to_notation(), notations[state], "state {state}");
+        }
+    }
+
+    #[test]
+    fn divergence_is_reported() {
+        let (mut replay, notations) = recorded_game(4);
+        let inputs = replay.inputs.len();
+        replay.record(Input::Undo(7));
+        replay.record(Input::Undo(0));
+
+        let mut states = ReplayStates::new(replay);
+
+        let diverged = states.diverged.as_ref().unwrap();
+        assert_eq!(diverged.input, inputs);
+        assert_eq!(diverged.error, ActionError::GameOver);
+        assert_eq!(states.last(), inputs);
+        assert_eq!(states.state(inputs + 1).to_notation(), notations[inputs]);
+    }
+}
@@ -0,0 +1,153 @@
+use rand::seq::SliceRandom;
+use strum::IntoEnumIterator;
+
+use super::{Game, log::GameEvent, city::CityIdx, disease::{DiseaseKind, CureStatus}, player::{Profession, PlayerId}, world::CURE_CARDS};
+
+// Hooks through which a profession changes the rules for its player
+impl Profession {
+    pub fn cure_cards(&self) -> usize {
+        match self {
+        Profession::Scientist => CURE_CARDS - 1,
+        _ => CURE_CARDS,
+        }
+    }
+
+    // Treating removes every cube of the colour instead of one
+    pub fn treats_all(&self) -> bool {
+        matches!(self, Profession::Medic)
+    }
+
+    // Share Knowledge may give away any city card, not only the current city's
+    pub fn shares_any_card(&self) -> bool {
+        matches!(self, Profession::Researcher)
+    }
+
+    pub fn moves_other_pawns(&self) -> bool {
+        matches!(self, Profession::Dispatcher)
+    }
+
+    pub fn builds_without_card(&self) -> bool {
+        matches!(self, Profession::Ops)
+    }
+
+    pub fn stores_events(&self) -> bool {
+        matches!(self, Profession::Contingency)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+        Profession::None => "No special abilities.",
+        Profession::Medic => "Removes all cubes of a colour when treating. Removes cubes of cured diseases from their city without an action and prevents them from being placed there.",
+        Profession::Scientist => "Needs only 4 cards of the same colour to discover a cure.",
+        Profession::Dispatcher => "Moves other players' pawns as if they were their own.",
+        Profession::Researcher => "Can give any city card when sharing knowledge.",
+        Profession::Ops => "Builds research stations without discarding a card.",
+        Profession::Contingency => "Can take an event card from the discard pile and keep it for later.",
+        Profession::QuarantineSpec => "Prevents cubes from being placed in their city and the cities connected to it.",
+        }
+    }
+}
+
+impl Game {
+    // Whether a role prevents a cube of `kind` from being placed in `city`
+    pub fn infection_blocked(&self, city: CityIdx, kind: DiseaseKind) -> bool {
+        self.players.iter().any(|p| match p.profession {
+            Profession::QuarantineSpec => {
+                p.current_city == city || self.world.map.connected(p.current_city, city)
+            },
+            Profession::Medic => {
+                p.current_city == city && self.world.cure_status(kind) != CureStatus::Active
+            },
+            _ => false,
+        })
+    }
+
+    // Let the roles react to the pawn of `player` arriving in a city
+    pub(crate) fn on_pawn_moved(&mut self, player: PlayerId) {
+        if self.players[player].profession == Profession::Medic {
+            self.medic_treat(player);
+        }
+    }
+
+    // Let the roles react to a cure being discovered
+    pub(crate) fn on_cure(&mut self) {
+        for player in 0..self.players.len() {
+            if self.players[player].profession == Profession::Medic {
+                self.medic_treat(player);
+            }
+        }
+    }
+
+    fn medic_treat(&mut self, player: PlayerId) {
+        let city = self.players[player].current_city;
+        for kind in DiseaseKind::iter() {
+            if self.world.cure_status(kind) != CureStatus::Active {
+                let removed = self.world.remove_cubes(city, kind, u8::MAX);
+                if removed > 0 {
+                    self.emit(GameEvent::CubesTreated { city, kind, removed });
+                }
+            }
+        }
+    }
+
+    // Give a random free profession to every player that did not choose one
+    pub fn assign_roles(&mut self) {
+        // Shuffle every profession so the seed gives the same setup whatever
+        // the players chose, which lets a notation replay with the final roles
+        let mut free: Vec<Profession> = Profession::iter().filter(|role| *role != Profession::None).collect();
+        free.shuffle(&mut self.rng);
+        free.retain(|role| self.players.iter().all(|p| p.profession != *role));
+
+        for player in self.players.iter_mut().filter(|p| p.profession == Profession::None) {
+            if let Some(role) = free.pop() {
+                player.profession = role;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, cards::Action, disease::{DiseaseKind, CureStatus}, log::GameEvent, player::Profession, turn::Turn};
+
+    // A game where the first player is a Medic about to act, next to a
+    // city with two Blue cubes
+    fn medic_game() -> (Game, usize) {
+        let mut game = new_game(&shipped_config(), 2, 2);
+        game.players[0].profession = Profession::Medic;
+        game.players[1].profession = Profession::Scientist;
+        game.current_player = 0;
+        game.turn = Turn::Action(4);
+
+        let from = game.players[0].current_city;
+        let city = game.world.map.neighbours(from).next().unwrap();
+        game.world.cities[city].cubes = Default::default();
+        game.world.place_cube(city, DiseaseKind::Blue);
+        game.world.place_cube(city, DiseaseKind::Blue);
+
+        (game, city)
+    }
+
+    #[test]
+    fn medic_removes_cured_cubes_on_arrival() {
+        let (mut game, city) = medic_game();
+        game.world.cures[DiseaseKind::Blue.idx()] = CureStatus::Cured;
+        game.log.clear();
+
+        game.apply_action(0, Action::Drive(city, None)).unwrap();
+
+        assert_eq!(game.world.cities[city].cubes(DiseaseKind::Blue), 0);
+        assert!(game.log.contains(&GameEvent::CubesTreated { city, kind: DiseaseKind::Blue, removed: 2 }));
+    }
+
+    #[test]
+    fn medic_leaves_uncured_cubes() {
+        let (mut game, city) = medic_game();
+        game.log.clear();
+
+        game.apply_action(0, Action::Drive(city, None)).unwrap();
+
+        assert_eq!(game.world.cities[city].cubes(DiseaseKind::Blue), 2);
+        assert!(!game.log.iter().any(|event| matches!(event, GameEvent::CubesTreated { .. })));
+    }
+}
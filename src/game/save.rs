@@ -0,0 +1,81 @@
+use std::{error::Error, fs::File, path::Path};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_yaml::with::singleton_map_recursive;
+
+use super::Game;
+
+// Bumped whenever a change to `Game` breaks older save files
+pub const SAVE_VERSION: u32 = 4;
+pub const AUTOSAVE_FILE: &str = "autosave.bin";
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<G> {
+    version: u32,
+    game: G,
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml"))
+}
+
+// Written as YAML for `.yaml`/`.yml` files and as bincode otherwise
+pub(crate) fn write_file<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+
+    // Enums are written as single entry maps, plain YAML cannot nest them
+    if is_yaml(path) {
+        singleton_map_recursive::serialize(value, &mut serde_yaml::Serializer::new(file))?;
+    } else {
+        bincode::serialize_into(file, value)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let file = File::open(path)?;
+
+    if is_yaml(path) {
+        Ok(singleton_map_recursive::deserialize(serde_yaml::Deserializer::from_reader(file))?)
+    } else {
+        Ok(bincode::deserialize_from(file)?)
+    }
+}
+
+impl Game {
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        write_file(path, &SaveFile { version: SAVE_VERSION, game: self })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let save: SaveFile<Game> = read_file(path)?;
+
+        if save.version != SAVE_VERSION {
+            return Err(format!("save file version {} is not supported, expected {}", save.version, SAVE_VERSION).into());
+        }
+
+        Ok(save.game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Game, tests::{new_game, shipped_config}};
+
+    #[test]
+    fn saved_game_loads_back() {
+        let game = new_game(&shipped_config(), 11, 3);
+
+        for file in ["rustdemic_test_save.bin", "rustdemic_test_save.yaml"] {
+            let path = std::env::temp_dir().join(file);
+            game.save(&path).unwrap();
+            let loaded = Game::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded.to_notation(), game.to_notation());
+            assert_eq!(loaded.world.disease_deck.cards_stack, game.world.disease_deck.cards_stack);
+            assert_eq!(loaded.world.play_deck.cards_stack, game.world.play_deck.cards_stack);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Game, cards::{PlayCard, DiseaseCard}, city::CityIdx, log::GameEvent, player::PlayerId};
+
+// Cubes placed by each round of the initial infection
+const SETUP_INFECTIONS: [u8; 3] = [3, 2, 1];
+// Infection cards drawn in each round
+const SETUP_INFECTION_CARDS: usize = 3;
+
+impl Game {
+    // Prepare the board once all players have joined: place the pawns,
+    // infect the first cities, deal the hands and pick who starts
+    pub fn setup(&mut self) {
+        for cubes in SETUP_INFECTIONS {
+            for _ in 0..SETUP_INFECTION_CARDS {
+                if let Some(DiseaseCard::City(city)) = self.world.disease_deck.draw() {
+                    self.setup_infection(city, cubes);
+                }
+            }
+        }
+        self.extend_infection_deck();
+
+        for pawn in 0..self.players.len() {
+            self.players[pawn].current_city = self.world.start_city;
+        }
+
+        let epidemics = self.epidemic_cards();
+        self.build_play_deck(epidemics);
+
+        self.current_player = self.starting_player();
+    }
+
+    // Cubes of the initial infection are placed whatever the roles, and
+    // never cause an outbreak
+    fn setup_infection(&mut self, city: CityIdx, cubes: u8) {
+        let kind = self.world.cities[city].kind;
+        let placed = (0..cubes).filter(|_| self.world.place_cube(city, kind)).count() as u8;
+        self.emit(GameEvent::CityInfected { city, kind, cubes: placed });
+    }
+
+    // The player holding the city card with the highest population starts
+    pub fn starting_player(&self) -> PlayerId {
+        let highest_population = |cards: &Vec<PlayCard>| cards.iter()
+            .filter_map(|card| match card {
+                PlayCard::City(city) => Some(self.world.cities[*city].population),
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default();
+
+        (0..self.players.len())
+            .max_by_key(|p| highest_population(&self.players[*p].cards))
+            .unwrap_or_default()
+    }
+
+    // Cards dealt to each player at the start of the game
+    pub fn starting_hand_size(&self) -> usize {
+        match self.players.len() {
+        0..=2 => 4,
+        3 => 3,
+        _ => 2,
+        }
+    }
+
+    // Deal the starting hands, then split the rest of the play deck into one
+    // pile per card in `epidemics`. Variants can pass their own epidemic cards.
+    pub fn build_play_deck(&mut self, epidemics: Vec<PlayCard>) {
+        for _ in 0..self.starting_hand_size() {
+            for player in self.players.iter_mut() {
+                if let Some((_, card)) = self.world.play_deck.take() {
+                    player.add_card(card);
+                }
+            }
+        }
+
+        self.world.play_deck.shuffle_into_piles(epidemics, &mut self.rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, cards::PlayCard, log::GameEvent, player::{Player, Profession}};
+
+    use super::{SETUP_INFECTIONS, SETUP_INFECTION_CARDS};
+
+    fn cubes_on_board(game: &Game) -> u32 {
+        game.world.cities.iter().map(|city| city.cubes.iter().map(|c| *c as u32).sum::<u32>()).sum()
+    }
+
+    #[test]
+    fn roles_do_not_prevent_setup_infections() {
+        let expected: u32 = SETUP_INFECTIONS.iter().map(|cubes| *cubes as u32 * SETUP_INFECTION_CARDS as u32).sum();
+
+        for seed in 0..50 {
+            let mut game = Game::from_config(&shipped_config(), Some(seed));
+            for (id, profession) in [Profession::QuarantineSpec, Profession::Medic].into_iter().enumerate() {
+                game.players.push(Player { name: format!("Player {id}"), id, profession, ..Default::default() });
+            }
+            game.setup();
+
+            assert_eq!(cubes_on_board(&game), expected, "seed {seed}");
+            assert!(!game.log.iter().any(|event| matches!(event, GameEvent::InfectionPrevented { .. })));
+        }
+    }
+
+    #[test]
+    fn setup_places_pawns_and_deals_hands() {
+        let game = new_game(&shipped_config(), 3, 3);
+        let start = game.world.start_city;
+
+        assert!(game.players.iter().all(|p| p.current_city == start));
+        assert!(game.players.iter().all(|p| p.cards.len() == game.starting_hand_size()));
+        assert_eq!(game.world.disease_deck.cards_discard.len(), SETUP_INFECTIONS.len() * SETUP_INFECTION_CARDS);
+
+        // The starting player holds the most populous city in hand
+        let population = |p: &Player| p.cards.iter().filter_map(|card| match card {
+            PlayCard::City(city) => Some(game.world.cities[*city].population),
+            _ => None,
+        }).max().unwrap_or_default();
+        let best = game.players.iter().map(population).max().unwrap();
+        assert_eq!(population(&game.players[game.current_player]), best);
+    }
+}
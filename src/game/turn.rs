@@ -1,17 +1,26 @@
 use serde::{Serialize, Deserialize};
 
+use super::player::PlayerId;
+
 pub type ActionsLeft = u8;
 pub type DrawsLeft = u8;
 pub type DiseasesLeft = u8;
+pub type WindowId = usize;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Turn {
     Action(ActionsLeft),
     Draw(DrawsLeft),
     Disease(DiseasesLeft),
-    PandemicInfect,
-    PandemicIntensify,
+    // Epidemics are resolved as soon as they are drawn, remembering how many
+    // draws are left so that a second epidemic in the same phase also resolves
+    PandemicInfect(DrawsLeft),
+    PandemicIntensify(DrawsLeft),
     NextPlayer,
+    // A player is over the hand limit and has to discard before `Turn` resumes
+    Discard(PlayerId, Box<Turn>),
+    // The players still waiting may play an event card before `Turn` resumes
+    EventWindow(WindowId, Vec<PlayerId>, Box<Turn>),
 }
 
 
@@ -41,15 +50,15 @@ impl Turn {
         }
     }
 
-    pub fn draw_card(&self, is_epidemic: bool) -> Option<Turn> {
+    pub fn draw_card(&self, is_epidemic: bool, infection_rate: DiseasesLeft) -> Option<Turn> {
         match self {
         Turn::Draw(left) => {
             if *left == 0 {
                 None
-            } else if *left == 1 {
-                Some(Turn::Disease(2))
             } else if is_epidemic {
-                Some(Turn::PandemicInfect)
+                Some(Turn::PandemicInfect(left - 1))
+            } else if *left == 1 {
+                Some(Turn::Disease(infection_rate))
             } else {
                 Some(Turn::Draw(left - 1))
             }
@@ -60,14 +69,20 @@ impl Turn {
 
     pub fn pandemic_infect(&self) -> Option<Turn> {
         match self {
-        Turn::PandemicInfect => Some(Turn::PandemicIntensify),
+        Turn::PandemicInfect(left) => Some(Turn::PandemicIntensify(*left)),
         _ => None
         }
     }
 
-    pub fn pandemic_intensify(&self) -> Option<Turn> {
+    pub fn pandemic_intensify(&self, infection_rate: DiseasesLeft) -> Option<Turn> {
         match self {
-        Turn::PandemicIntensify => Some(Turn::Disease(2)),
+        Turn::PandemicIntensify(left) => {
+            if *left == 0 {
+                Some(Turn::Disease(infection_rate))
+            } else {
+                Some(Turn::Draw(*left))
+            }
+        },
         _ => None
         }
     }
@@ -87,6 +102,30 @@ impl Turn {
         }
     }
 
+    // Phases which are played out by the engine without asking the players
+    pub fn is_automatic(&self) -> bool {
+        !matches!(self, Turn::Action(_) | Turn::Discard(_, _) | Turn::EventWindow(_, _, _))
+    }
+
+    // Steps before which the players get a chance to play an event card
+    pub fn is_event_point(&self) -> bool {
+        matches!(self, Turn::Draw(_) | Turn::Disease(_) | Turn::PandemicIntensify(_))
+    }
+
+    pub fn close_event_window(&self) -> Option<Turn> {
+        match self {
+        Turn::EventWindow(_, _, resume) => Some(*resume.clone()),
+        _ => None,
+        }
+    }
+
+    pub fn discard(&self) -> Option<Turn> {
+        match self {
+        Turn::Discard(_, resume) => Some(*resume.clone()),
+        _ => None,
+        }
+    }
+
     pub fn advance_next_player(&self) -> Option<Turn> {
         match self {
         Turn::NextPlayer => Some(Self::new()),
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Game, actions::ActionError, log::GameEvent, player::PlayerId, turn::Turn};
+
+// Snapshots of the game taken before each action of the current player.
+// They are dropped as soon as a card is drawn, which reveals hidden information.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct UndoHistory {
+    undo: Vec<Game>,
+    redo: Vec<Game>,
+}
+
+impl Game {
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    // Take back the last action of `player` in the current action phase
+    pub fn undo(&mut self, player: PlayerId) -> Result<(), ActionError> {
+        self.check_undo(player)?;
+
+        let snapshot = self.history.undo.pop().ok_or(ActionError::NothingToUndo)?;
+        let undone = self.restore(snapshot);
+        self.history.redo.push(undone);
+
+        self.emit(GameEvent::ActionUndone(player));
+        Ok(())
+    }
+
+    // Play again the last action `player` took back
+    pub fn redo(&mut self, player: PlayerId) -> Result<(), ActionError> {
+        self.check_undo(player)?;
+
+        let snapshot = self.history.redo.pop().ok_or(ActionError::NothingToRedo)?;
+        let redone = self.restore(snapshot);
+        self.history.undo.push(redone);
+
+        self.emit(GameEvent::ActionRedone(player));
+        Ok(())
+    }
+
+    // Remember the game as it is before an action
+    pub(crate) fn save_undo(&mut self) {
+        let mut snapshot = self.clone();
+        snapshot.history = UndoHistory::default();
+        snapshot.log.clear();
+
+        self.history.undo.push(snapshot);
+        self.history.redo.clear();
+    }
+
+    pub(crate) fn forget_undo(&mut self) {
+        self.history = UndoHistory::default();
+    }
+
+    fn check_undo(&self, player: PlayerId) -> Result<(), ActionError> {
+        if self.is_over() {
+            return Err(ActionError::GameOver);
+        }
+
+        if player != self.current_player {
+            return Err(ActionError::NotYourTurn(player));
+        }
+
+        if !matches!(self.turn, Turn::Action(_)) {
+            return Err(ActionError::NoActionsLeft);
+        }
+
+        if let Some(pending) = &self.pending {
+            return Err(ActionError::AwaitingConsent(pending.target));
+        }
+
+        Ok(())
+    }
+
+    // Put the game back to `snapshot`, keeping the undo history and the log,
+    // and return the game as it was without them
+    fn restore(&mut self, snapshot: Game) -> Game {
+        let mut previous = std::mem::replace(self, snapshot);
+        self.history = std::mem::take(&mut previous.history);
+        self.log = std::mem::take(&mut previous.log);
+
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{tests::{new_game, shipped_config}, Game, actions::ActionError, cards::Action, log::GameEvent, turn::Turn};
+
+    fn acting_game() -> (Game, usize, usize) {
+        let mut game = new_game(&shipped_config(), 9, 2);
+        for player in game.players.iter_mut() {
+            player.cards.clear();
+        }
+        game.current_player = 0;
+        game.turn = Turn::Action(4);
+        let from = game.players[0].current_city;
+        let to = game.world.map.neighbours(from).next().unwrap();
+
+        (game, from, to)
+    }
+
+    #[test]
+    fn undo_and_redo_an_action() {
+        let (mut game, from, to) = acting_game();
+        assert_eq!(game.undo(0), Err(ActionError::NothingToUndo));
+
+        game.apply_action(0, Action::Drive(to, None)).unwrap();
+        assert_eq!(game.undo(1), Err(ActionError::NotYourTurn(1)));
+
+        game.undo(0).unwrap();
+        assert_eq!(game.players[0].current_city, from);
+        assert!(matches!(game.turn, Turn::Action(4)));
+        assert!(game.can_redo() && !game.can_undo());
+        assert_eq!(game.log.last(), Some(&GameEvent::ActionUndone(0)));
+
+        game.redo(0).unwrap();
+        assert_eq!(game.players[0].current_city, to);
+        assert!(matches!(game.turn, Turn::Action(3)));
+        assert!(game.can_undo() && !game.can_redo());
+    }
+
+    #[test]
+    fn new_action_forgets_the_redo() {
+        let (mut game, from, to) = acting_game();
+
+        game.apply_action(0, Action::Drive(to, None)).unwrap();
+        game.undo(0).unwrap();
+        let other = game.world.map.neighbours(from).find(|c| *c != to).unwrap();
+        game.apply_action(0, Action::Drive(other, None)).unwrap();
+
+        assert_eq!(game.redo(0), Err(ActionError::NothingToRedo));
+    }
+
+    #[test]
+    fn drawing_forgets_the_history() {
+        let (mut game, from, to) = acting_game();
+
+        for city in [to, from, to, from] {
+            game.apply_action(0, Action::Drive(city, None)).unwrap();
+        }
+        assert_eq!(game.undo(0), Err(ActionError::NoActionsLeft));
+
+        game.advance();
+        assert!(!game.can_undo());
+    }
+}
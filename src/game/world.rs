@@ -2,16 +2,108 @@ use serde::{Deserialize, Serialize};
 
 use crate::util::graph::Graph;
 
-use super::{city::City, cards::{Deck, PlayCard, DiseaseCard}};
+use super::{city::{City, CityIdx}, cards::{Deck, PlayCard, DiseaseCard}, disease::{NUM_DISEASES, DiseaseKind, CureStatus}};
 
 pub(crate) const IMACT_RATE: [u8; 8] = [2, 2, 2, 3, 3, 3, 4, 4];
+pub(crate) const MAX_OUTBREAKS: u8 = 8;
+pub(crate) const DEFAULT_CUBES: u8 = 24;
+pub(crate) const CURE_CARDS: usize = 5;
+pub(crate) const MAX_RESEARCH_STATIONS: usize = 6;
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct World {
     pub map: Graph,
     pub cities: Vec<City>,
     pub play_deck: Deck<PlayCard>,
     pub disease_deck: Deck<DiseaseCard>,
     pub outbreaks: u8,
-    pub impaction_rate: u8
+    pub impaction_rate: u8,
+    // Cubes left in the supply for each disease
+    pub cubes: [u8; NUM_DISEASES],
+    pub cures: [CureStatus; NUM_DISEASES],
+    pub research_stations: Vec<CityIdx>,
+    // Diseases which have to be cured to win
+    pub diseases: Vec<DiseaseKind>,
+    // Where the pawns and the first research station start
+    pub start_city: CityIdx,
+}
+
+impl World {
+    pub fn infection_rate(&self) -> u8 {
+        IMACT_RATE[self.impaction_rate as usize]
+    }
+
+    pub fn cubes_left(&self, kind: DiseaseKind) -> u8 {
+        self.cubes[kind.idx()]
+    }
+
+    // Take a cube of `kind` from the supply and put it in `city`.
+    // Returns false if the supply has run out.
+    pub fn place_cube(&mut self, city: CityIdx, kind: DiseaseKind) -> bool {
+        if self.cubes[kind.idx()] == 0 {
+            return false;
+        }
+
+        self.cubes[kind.idx()] -= 1;
+        self.cities[city].cubes[kind.idx()] += 1;
+
+        true
+    }
+
+    // Remove up to `count` cubes of `kind` from `city` and return them to the supply.
+    // Removing the last cube of a cured disease eradicates it.
+    pub fn remove_cubes(&mut self, city: CityIdx, kind: DiseaseKind, count: u8) -> u8 {
+        let cubes = &mut self.cities[city].cubes[kind.idx()];
+        let removed = count.min(*cubes);
+
+        *cubes -= removed;
+        self.cubes[kind.idx()] += removed;
+
+        if self.cure_status(kind) == CureStatus::Cured && self.cubes_on_board(kind) == 0 {
+            self.cures[kind.idx()] = CureStatus::Eradicated;
+        }
+
+        removed
+    }
+
+    pub fn cubes_on_board(&self, kind: DiseaseKind) -> u8 {
+        self.cities.iter().map(|c| c.cubes(kind)).sum()
+    }
+
+    pub fn cure_status(&self, kind: DiseaseKind) -> CureStatus {
+        self.cures[kind.idx()]
+    }
+
+    pub fn cure(&mut self, kind: DiseaseKind) {
+        self.cures[kind.idx()] = if self.cubes_on_board(kind) == 0 {
+            CureStatus::Eradicated
+        } else {
+            CureStatus::Cured
+        };
+    }
+
+    pub fn all_cured(&self) -> bool {
+        self.diseases.iter().all(|kind| self.cure_status(*kind) != CureStatus::Active)
+    }
+
+    pub fn has_research_station(&self, city: CityIdx) -> bool {
+        self.research_stations.contains(&city)
+    }
+
+    // Build a station in `city`, taking it from `relocate` if all stations are used up
+    pub fn build_research_station(&mut self, city: CityIdx, relocate: Option<CityIdx>) {
+        if let Some(from) = relocate {
+            self.research_stations.retain(|c| *c != from);
+        }
+
+        if self.research_stations.len() < MAX_RESEARCH_STATIONS && !self.has_research_station(city) {
+            self.research_stations.push(city);
+        }
+    }
+
+    pub fn increase_impaction_rate(&mut self) {
+        if (self.impaction_rate as usize) < IMACT_RATE.len() - 1 {
+            self.impaction_rate += 1;
+        }
+    }
 }
\ No newline at end of file
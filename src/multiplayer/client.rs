@@ -1,8 +1,8 @@
-use std::{io::Read, net::SocketAddr, collections::HashMap, thread::JoinHandle, sync::{Arc, Mutex}};
+use std::sync::{Arc, Mutex};
 
-use message_io::{node::{NodeHandler, NodeListener, self, NodeEvent, NodeTask}, network::{Transport, Endpoint, NetEvent}};
+use message_io::{node::{NodeHandler, NodeListener, self, NodeEvent}, network::{Transport, Endpoint, NetEvent}};
 
-use crate::game::{world::World, player::{Player, PlayerId}, Game};
+use crate::game::{player::Player, Game, actions::{ActionError, PendingAction}};
 
 use super::{ClientMsg, ServerMsg, MsgHash};
 
@@ -16,26 +16,26 @@ pub struct Client {
 
     pub player: Player,
 
+    // Reason the server rejected our last request
+    pub last_error: Option<ActionError>,
+    // Another player's action waiting for our consent
+    pub consent_prompt: Option<PendingAction>,
+    // Open event window we may still pass on
+    pub event_prompt: Option<MsgHash>,
+
     addr: String,
     endpoint: Option<Endpoint>,
 
-    requests: HashMap<MsgHash, ClientMsg>,
     handler: Option<NodeHandler<Signal>>,
     listener: Option<NodeListener<Signal>>,
 
-    node_task: Option<NodeTask>,
-
     connected: bool,
-    is_server: bool,
-}
 
-#[derive(Debug)]
-pub enum ClientError {
-    NotConnected,
+    pub started: bool,
 }
 
 impl Client {
-    pub fn new(name: &str, addr: &str, is_server: bool) -> Option<Self> {
+    pub fn new(name: &str, addr: &str) -> Option<Self> {
         let (handler, listener) = node::split();
 
         Some(Self {
@@ -44,26 +44,40 @@ impl Client {
                 name: name.to_string(), 
                 ..Default::default()
             },
+            last_error: None,
+            consent_prompt: None,
+            event_prompt: None,
             addr: addr.to_string(),
             endpoint: None,
-            requests: HashMap::new(),
             handler: Some(handler),
             listener: Some(listener),
-            node_task: None,
             connected: false,
-            is_server,
+            started: false,
         })
     }
 
     fn update_players(&mut self, v: Vec<Player>) {
+        if let Some(id) = v.iter().position(|p| p.name == self.player.name) {
+            self.player.id = id;
+        }
         self.game.players = v;
     }
 
-    pub fn players_map<F>(&self, f: F) where F: Fn(&Player) {
-        for player in self.game.players.iter() {
-            f(player);
+    pub fn send(&mut self, msg: ClientMsg) {
+        if let Some(endpoint) = self.endpoint {
+            let data = bincode::serialize(&msg).unwrap();
+            self.handler.as_ref().unwrap().network().send(endpoint, &data);
+            self.last_error = None;
         }
     }
+
+    pub fn stop(&mut self) {
+        if self.handler.is_none() {
+            return
+        }
+
+        self.handler.as_ref().unwrap().stop();
+    }
 }
 
 pub fn start_client(client: Arc<Mutex<Client>>) {
@@ -94,11 +108,34 @@ pub fn start_client(client: Arc<Mutex<Client>>) {
                         ServerMsg::AddPlayer(players) => {
                             client.update_players(players);
                         },
+                        ServerMsg::Started(_) => {
+                            client.started = true;
+                        },
+                        ServerMsg::SyncGame(game) => {
+                            client.game = *game;
+                        },
+                        ServerMsg::ActionRejected(e) => {
+                            println!("Action rejected: {}", e);
+                            client.last_error = Some(e);
+                        },
+                        ServerMsg::PromptConsent(pending) => {
+                            client.consent_prompt = Some(pending);
+                        },
+                        ServerMsg::PromptEvent(hash) => {
+                            client.event_prompt = Some(hash);
+                        },
+                        ServerMsg::GameOver(outcome) => {
+                            client.game.outcome = Some(outcome);
+                        },
                         _ => todo!(),
                         }
                     },
                     NetEvent::Disconnected(_endpoint) => {
-                        client.lock().unwrap().handler.as_ref().unwrap().stop();
+                        let mut client = client.lock().unwrap();
+                        if client.started && !client.game.is_over() {
+                            client.game.abandon();
+                        }
+                        client.handler.as_ref().unwrap().stop();
                         println!("Bye bye!");
                     },
                 },
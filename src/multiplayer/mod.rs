@@ -1,4 +1,4 @@
-use crate::game::player::{PlayerId, Player};
+use crate::game::{player::{Player, Profession}, Game, GameOutcome, cards::{Action, PlayCard, Event}, actions::{ActionError, PendingAction}};
 
 pub mod client;
 pub mod server;
@@ -15,6 +15,10 @@ pub enum ServerMsg {
 
     Started(MsgHash),
     AddPlayer(Vec<Player>),
+    SyncGame(Box<Game>),
+    ActionRejected(ActionError),
+    PromptConsent(PendingAction),
+    GameOver(GameOutcome),
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -25,11 +29,12 @@ pub enum ClientMsg {
 
     Start(MsgHash),
 
-    CardDiscarded,
-    Move,
-    Treat,
-    Cure,
-    Build,
-    Event,
+    CardDiscarded(Vec<PlayCard>),
+    Action(Action),
+    Consent(bool),
+    ChooseRole(Profession),
+    Event(Event),
     RejectEvent(MsgHash),
+    Undo,
+    Redo,
 }
\ No newline at end of file
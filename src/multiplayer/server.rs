@@ -1,13 +1,16 @@
-use std::{path::PathBuf, collections::HashMap, net::{SocketAddr, IpAddr, Ipv4Addr}, default, sync::{Arc, Mutex}, thread::JoinHandle};
+use std::{path::{Path, PathBuf}, collections::HashMap, net::{SocketAddr, IpAddr, Ipv4Addr}, sync::{Arc, Mutex}, time::Duration};
 
-use message_io::{node::{NodeHandler, NodeListener, self, NodeEvent, NodeTask}, network::{Endpoint, Transport, NetEvent}};
+use message_io::{node::{NodeHandler, NodeListener, self, NodeEvent}, network::{Endpoint, Transport, NetEvent}};
 
-use crate::game::{Game, player::{Player, PlayerId, self}};
+use crate::{util::config::GameConfig, game::{Game, GameOutcome, save::AUTOSAVE_FILE, replay::{Replay, Input, REPLAY_FILE}, notation::{NOTATION_FILE, check_player_name}, player::{Player, PlayerId, Profession}, actions::{ActionOutcome, ActionError}, turn::{Turn, WindowId}}};
 
 use super::{MsgHash, ServerMsg, ClientMsg};
 
+// How long the players get to play an event before the game goes on
+const EVENT_WINDOW_TIMEOUT: Duration = Duration::from_secs(10);
+
 enum Signal {
-    SendStart,
+    EventTimeout(MsgHash),
 }
 
 #[derive(Default)]
@@ -19,39 +22,65 @@ pub struct Server {
 
     handler: Option<NodeHandler<Signal>>,
     listener: Option<NodeListener<Signal>>,
-    //node_task: Option<NodeTask>,
-    node_task: Option<JoinHandle<()>>,
 
     addr: Option<SocketAddr>,
 
-    requests: HashMap<MsgHash, ServerMsg>,
+    next_hash: MsgHash,
+
+    // Event window the players were last prompted for
+    event_window: Option<(WindowId, MsgHash)>,
 
     started: bool,
-}
+    // The game was loaded from a save file
+    resumed: bool,
 
-#[derive(Debug)]
-pub enum ServerError {
-    NotConnected
+    // Config a new game was created from, for the replay
+    config: Option<GameConfig>,
+    // Inputs of the game so far. Loaded games are not recorded.
+    replay: Option<Replay>,
 }
 
 impl Server {
-    pub fn new(port: &str) -> Option<Self> {
+    pub fn new(port: &str, seed: Option<u64>) -> Option<Self> {
+        if let Ok(config) = GameConfig::from_file(Path::new("assets/config.yaml")) {
+            let game = Game::from_config(&config, seed);
+            let mut server = Self::with_game(port, game, false);
+            server.config = Some(config);
+            return Some(server);
+        }
+        None
+    }
+
+    // Host a saved game, whose players take back their seats by name
+    pub fn load(port: &str, path: &Path) -> Option<Self> {
+        match Game::load(path) {
+        Ok(game) => Some(Self::with_game(port, game, true)),
+        Err(e) => {
+            println!("Failed to load {}: {}", path.display(), e);
+            None
+        },
+        }
+    }
+
+    fn with_game(port: &str, mut game: Game, resumed: bool) -> Self {
         let (handler, listener) = node::split();
 
-        if let Ok(game) = Game::from_file(PathBuf::from("assets/config.yaml")) {
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port.parse::<u16>().unwrap());
-            return Some(Self {
-                game,
-                clients: HashMap::new(),
-                handler: Some(handler),
-                listener: Some(listener),
-                node_task: None,
-                addr: Some(addr),
-                requests: HashMap::new(),
-                started: false,
-            })
+        game.autosave = Some(PathBuf::from(AUTOSAVE_FILE));
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port.parse::<u16>().unwrap());
+        Self {
+            game,
+            clients: HashMap::new(),
+            handler: Some(handler),
+            listener: Some(listener),
+            addr: Some(addr),
+            next_hash: 0,
+            event_window: None,
+            started: false,
+            resumed,
+            config: None,
+            replay: None,
         }
-        None
     }
 
     fn update_players(&self) {
@@ -62,22 +91,129 @@ impl Server {
         }
     }
 
-    pub fn stop(&mut self) {
-        if self.handler.is_none() {
-            return
+    fn new_hash(&mut self) -> MsgHash {
+        self.next_hash += 1;
+        self.next_hash
+    }
+
+    fn broadcast(&self, msg: &ServerMsg) {
+        let data = bincode::serialize(msg).unwrap();
+        for client_endpoint in self.clients.keys() {
+            self.handler.as_ref().unwrap().network().send(*client_endpoint, &data);
         }
+    }
 
-        self.handler.as_ref().unwrap().stop();
+    pub fn start_game(&mut self) {
+        if self.started {
+            return;
+        }
+
+        if !self.resumed {
+            if let Some(config) = self.config.clone() {
+                self.replay = Some(Replay::new(config, &self.game));
+            }
+            self.game.assign_roles();
+            self.game.setup();
+        }
+        self.started = true;
+
+        let hash = self.new_hash();
+        self.broadcast(&ServerMsg::Started(hash));
+        // A loaded game may continue in the middle of automatic phases
+        self.advance_game();
     }
 
-    pub fn players_map<F>(&self, f: F) where F: Fn(&Player) {
-        for player in self.game.players.iter() {
-            f(player);
+    // Run the game until it needs input, prompting the players whenever it
+    // stops at a new event window
+    fn advance_game(&mut self) {
+        let outcome = self.game.advance();
+        self.sync_game();
+
+        if let Some(outcome) = outcome {
+            self.broadcast(&ServerMsg::GameOver(outcome));
+            self.save_replay();
+            return;
+        }
+
+        if let Turn::EventWindow(id, _, _) = self.game.turn {
+            if self.event_window.map(|(window, _)| window) != Some(id) {
+                let hash = self.new_hash();
+                self.event_window = Some((id, hash));
+                self.broadcast(&ServerMsg::PromptEvent(hash));
+                self.handler.as_ref().unwrap().signals().send_with_timer(Signal::EventTimeout(hash), EVENT_WINDOW_TIMEOUT);
+            }
+        }
+    }
+
+    fn record(&mut self, input: Input) {
+        if let Some(replay) = &mut self.replay {
+            replay.record(input);
+        }
+    }
+
+    fn save_replay(&self) {
+        if let Some(replay) = &self.replay {
+            match replay.save(Path::new(REPLAY_FILE)) {
+            Ok(()) => println!("Replay saved to {}", REPLAY_FILE),
+            Err(e) => println!("Failed to save the replay: {}", e),
+            }
+        }
+
+        match self.game.save_notation(Path::new(NOTATION_FILE)) {
+        Ok(()) => println!("Game notation saved to {}", NOTATION_FILE),
+        Err(e) => println!("Failed to save the game notation: {}", e),
+        }
+    }
+
+    // Whether `hash` belongs to the event window which is still open
+    fn event_window_open(&self, hash: MsgHash) -> bool {
+        match (self.event_window, &self.game.turn) {
+        (Some((window, prompt)), Turn::EventWindow(id, _, _)) => prompt == hash && window == *id,
+        _ => false,
+        }
+    }
+
+    fn sync_game(&self) {
+        self.broadcast(&ServerMsg::SyncGame(Box::new(self.game.clone())));
+    }
+
+    fn send_to(&self, player: PlayerId, msg: &ServerMsg) {
+        let data = bincode::serialize(msg).unwrap();
+        for (endpoint, id) in self.clients.iter() {
+            if *id == player {
+                self.handler.as_ref().unwrap().network().send(*endpoint, &data);
+            }
         }
     }
 
-    fn set_join_handle(&mut self, jh: JoinHandle<()>) {
-        self.node_task = Some(jh);
+    fn reject(&self, player: PlayerId, e: ActionError) {
+        self.send_to(player, &ServerMsg::ActionRejected(e));
+    }
+
+    // Free the lobby seat of `endpoint`. The players after it move up one
+    // seat so that ids keep matching their index in the game.
+    fn leave_lobby(&mut self, endpoint: Endpoint) -> Option<Player> {
+        let player_id = self.clients.remove(&endpoint)?;
+        let player = self.game.players.remove(player_id);
+
+        for (id, player) in self.game.players.iter_mut().enumerate() {
+            player.id = id;
+        }
+        for id in self.clients.values_mut() {
+            if *id > player_id {
+                *id -= 1;
+            }
+        }
+
+        Some(player)
+    }
+
+    pub fn stop(&mut self) {
+        if self.handler.is_none() {
+            return
+        }
+
+        self.handler.as_ref().unwrap().stop();
     }
 }
 
@@ -87,7 +223,7 @@ pub fn start_server(server: Arc<Mutex<Server>>) {
     let server_orig = server;
     let mut server = server_orig.lock().unwrap();
 
-    if let Err(_) = server.handler.as_ref().unwrap().network().listen(Transport::FramedTcp, &server.addr.unwrap()) {
+    if server.handler.as_ref().unwrap().network().listen(Transport::FramedTcp, server.addr.unwrap()).is_err() {
         println!("Error while starting server listen!");
         return
     }
@@ -104,21 +240,205 @@ pub fn start_server(server: Arc<Mutex<Server>>) {
                 NetEvent::Accepted(endpoint, _) => {
                     println!("Client connected!");
                     let mut server = server.lock().unwrap();
-                    
-                    server.game.players.push(Player::default());
-                    let players_cnt = server.game.players.len();
 
-                    server.clients.insert(endpoint, players_cnt - 1);
+                    // Seats of a loaded game are taken once the client tells its name
+                    if server.resumed {
+                        return;
+                    }
+
+                    // Every seat was dealt in when the game started
+                    if server.started {
+                        println!("The game has already started, dropping the client!");
+                        server.handler.as_ref().unwrap().network().remove(endpoint.resource_id());
+                        return;
+                    }
+
+                    let player_id = server.game.players.len();
+                    server.game.players.push(Player {
+                        id: player_id,
+                        ..Default::default()
+                    });
+
+                    server.clients.insert(endpoint, player_id);
                 },
                 NetEvent::Message(endpoint, data) => {
                     let msg: ClientMsg = bincode::deserialize(data).unwrap();
                     match msg {
                     ClientMsg::Id(name) => {
                         let mut server = server.lock().unwrap();
-                        let player_id = *server.clients.get(&endpoint).unwrap();
-                        server.game.players[player_id].name = name.clone();
+                        if server.resumed {
+                            match server.game.players.iter().position(|p| p.name == name) {
+                            Some(player_id) if !server.clients.values().any(|id| *id == player_id) => {
+                                println!("Player {} is back!", name);
+                                server.clients.insert(endpoint, player_id);
+                            },
+                            _ => {
+                                println!("Player {} has no free seat in the loaded game!", name);
+                                server.handler.as_ref().unwrap().network().remove(endpoint.resource_id());
+                                return;
+                            },
+                            }
+                        } else {
+                            let Some(&player_id) = server.clients.get(&endpoint) else {
+                                return;
+                            };
+
+                            // Names are written down as they are in the notation
+                            let refused = if server.game.players.iter().any(|p| p.name == name && p.id != player_id) {
+                                Some(format!("{name} is already taken"))
+                            } else {
+                                check_player_name(&name).err()
+                            };
+                            if let Some(e) = refused {
+                                println!("Refusing player {}: {}", name, e);
+                                server.leave_lobby(endpoint);
+                                server.handler.as_ref().unwrap().network().remove(endpoint.resource_id());
+                                server.update_players();
+                                return;
+                            }
+                            server.game.players[player_id].name = name.clone();
+                        }
 
                         server.update_players();
+                        server.sync_game();
+                    },
+                    ClientMsg::Action(action) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        let result = server.game.apply_action(player_id, action.clone());
+                        if result.is_ok() {
+                            server.record(Input::Action(player_id, action));
+                        }
+
+                        match result {
+                        Ok(ActionOutcome::AwaitingConsent(target)) => {
+                            let prompt = ServerMsg::PromptConsent(server.game.pending.clone().unwrap());
+                            server.send_to(target, &prompt);
+                            server.sync_game();
+                        },
+                        Ok(outcome) => {
+                            println!("Player {}: {:?}", server.game.players[player_id].name, outcome);
+                            server.advance_game();
+                        },
+                        Err(e) => server.reject(player_id, e),
+                        }
+                    },
+                    ClientMsg::Event(event) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        match server.game.play_event(player_id, event.clone()) {
+                        Ok(ActionOutcome::AwaitingConsent(target)) => {
+                            server.record(Input::Event(player_id, event));
+                            let prompt = ServerMsg::PromptConsent(server.game.pending.clone().unwrap());
+                            server.send_to(target, &prompt);
+                            server.sync_game();
+                        },
+                        Ok(outcome) => {
+                            server.record(Input::Event(player_id, event));
+                            println!("Player {}: {:?}", server.game.players[player_id].name, outcome);
+                            server.advance_game();
+                        },
+                        Err(e) => server.reject(player_id, e),
+                        }
+                    },
+                    ClientMsg::RejectEvent(hash) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        // Answers to a window which already closed are dropped
+                        if !server.event_window_open(hash) {
+                            return;
+                        }
+
+                        match server.game.pass_event(player_id) {
+                        Ok(()) => {
+                            server.record(Input::PassEvent(player_id));
+                            server.advance_game();
+                        },
+                        Err(e) => server.reject(player_id, e),
+                        }
+                    },
+                    ClientMsg::Undo | ClientMsg::Redo => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        let (result, input) = match msg {
+                        ClientMsg::Undo => (server.game.undo(player_id), Input::Undo(player_id)),
+                        _ => (server.game.redo(player_id), Input::Redo(player_id)),
+                        };
+
+                        match result {
+                        Ok(()) => {
+                            server.record(input);
+                            server.sync_game();
+                        },
+                        Err(e) => server.reject(player_id, e),
+                        }
+                    },
+                    ClientMsg::CardDiscarded(cards) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        match server.game.discard_cards(player_id, cards.clone()) {
+                        Ok(()) => {
+                            server.record(Input::Discard(player_id, cards));
+                            server.advance_game();
+                        },
+                        Err(e) => server.reject(player_id, e),
+                        }
+                    },
+                    ClientMsg::ChooseRole(role) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+
+                        let taken = role != Profession::None && server.game.players.iter().any(|p| p.profession == role && p.id != player_id);
+                        if !server.started && !server.resumed && !taken {
+                            server.game.players[player_id].profession = role;
+                            server.update_players();
+                        }
+                    },
+                    ClientMsg::Consent(accept) => {
+                        let mut server = server.lock().unwrap();
+                        let Some(&player_id) = server.clients.get(&endpoint) else {
+                            return;
+                        };
+                        let requester = server.game.pending.as_ref().map(|p| p.player);
+
+                        let result = server.game.answer_consent(player_id, accept);
+                        if !matches!(result, Err(ActionError::NothingToAnswer(_))) {
+                            server.record(Input::Consent(player_id, accept));
+                        }
+
+                        match result {
+                        Ok(ActionOutcome::Declined(target)) => {
+                            if let Some(requester) = requester {
+                                server.reject(requester, ActionError::Declined(target));
+                            }
+                            server.sync_game();
+                        },
+                        Ok(outcome) => {
+                            println!("Player {}: {:?}", server.game.players[player_id].name, outcome);
+                            server.advance_game();
+                        },
+                        Err(e) => {
+                            server.reject(player_id, e);
+                            server.sync_game();
+                        },
+                        }
                     },
                     _ => {
                         todo!()
@@ -127,16 +447,26 @@ pub fn start_server(server: Arc<Mutex<Server>>) {
                 },
                 NetEvent::Disconnected(endpoint) => {
                     let mut server = server.lock().unwrap();
-                    if server.started {
-                        let clients = &server.clients;
-                        println!("Client {} disconnected! End of session :/", server.game.players[*clients.get(&endpoint).unwrap()].name);
-                        server.handler.as_ref().unwrap().stop();
+                    let seated = server.clients.contains_key(&endpoint);
+                    if !seated {
+                        println!("Client without a seat disconnected!");
+                    } else if server.started {
+                        let player_id = server.clients.remove(&endpoint).unwrap();
+                        println!("Client {} disconnected! End of session :/", server.game.players[player_id].name);
+
+                        if !server.game.is_over() {
+                            server.game.abandon();
+                            server.sync_game();
+                            server.broadcast(&ServerMsg::GameOver(GameOutcome::Abandoned));
+                            server.save_replay();
+                        }
+                    } else if server.resumed {
+                        let player_id = server.clients.remove(&endpoint).unwrap();
+                        println!("Player {} left, their seat is free again", server.game.players[player_id].name);
                     } else {
-                        let player_id = *server.clients.get(&endpoint).unwrap();
-                        let name = &server.game.players[player_id].name;
-                        println!("Player {} disconnected!", name);
-                        
-                        server.game.players.remove(player_id);
+                        let player = server.leave_lobby(endpoint).unwrap();
+                        println!("Player {} disconnected!", player.name);
+
                         server.update_players();
                     }
                 }, //Tcp or Ws
@@ -144,9 +474,15 @@ pub fn start_server(server: Arc<Mutex<Server>>) {
             },
             NodeEvent::Signal(signal) => {
                 match signal {
-                Signal::SendStart => {
-                    server.lock().unwrap().started = true;
-                }
+                Signal::EventTimeout(hash) => {
+                    let mut server = server.lock().unwrap();
+                    if server.event_window_open(hash) {
+                        println!("Nobody played an event, the game goes on");
+                        server.game.close_event_window();
+                        server.record(Input::CloseEventWindow);
+                        server.advance_game();
+                    }
+                },
                 }
             },
             };
@@ -1,19 +1,33 @@
-use serde::Deserialize;
+use std::{error::Error, path::Path};
 
-use crate::game::{disease::DiseaseKind, Difficulty};
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+use crate::game::{disease::DiseaseKind, city::CityIdx, challenge::Challenge, Difficulty};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphDef(pub Vec<usize>);
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CityDef {
     pub name: String,
     pub kind: DiseaseKind,
+    pub population: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameConfig {
     pub cities: Vec<CityDef>,
     pub map: GraphDef,
     pub difficulty: Option<Difficulty>,
-}
\ No newline at end of file
+    pub cubes_per_disease: Option<u8>,
+    // Supply of the purple disease of the Mutation challenge
+    pub purple_cubes: Option<u8>,
+    pub start_city: Option<CityIdx>,
+    pub seed: Option<u64>,
+    pub challenge: Option<Challenge>,
+}
+impl GameConfig {
+    pub fn from_file(yaml_file: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_yaml::from_reader(std::fs::File::open(yaml_file)?)?)
+    }
+}
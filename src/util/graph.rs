@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 #[derive(Debug)]
 pub struct Graph {
     graph: Vec<Vec<bool>>,
@@ -34,5 +34,10 @@ impl Graph {
 
         self.graph[v1][v2]
     }
-}
 
+    pub fn neighbours(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        assert!(v < self.graph.len());
+
+        self.graph[v].iter().enumerate().filter(|(_, c)| **c).map(|(i, _)| i)
+    }
+}
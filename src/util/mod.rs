@@ -1,2 +1,3 @@
 pub mod graph;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod rng;
\ No newline at end of file